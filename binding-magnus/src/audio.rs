@@ -15,10 +15,18 @@
 // You should have received a copy of the GNU General Public License
 // along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
+#[cfg(feature = "modshot")]
+use magnus::TryConvert;
 use magnus::{function, Value};
 
+use magnus::{exception::ExceptionClass, value::Lazy, Module, RModule};
+
+use librgss::{AudioChannel, AudioError, AudioErrorMode, RgssVersion, VolumeCategory};
 use parking_lot::RwLock;
 use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::get_rgss_version;
 
 // FIXME find a way around using a static
 pub(crate) static AUDIO: OnceLock<RwLock<librgss::Audio>> = OnceLock::new();
@@ -30,61 +38,125 @@ pub fn get_audio() -> &'static RwLock<librgss::Audio> {
         .expect("audio static not set! please report how you encountered this crash")
 }
 
-fn bgm_play(args: &[Value]) -> Result<(), magnus::Error> {
-    #[cfg(not(feature = "modshot"))]
-    {
-        let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+static ERROR_MODE: OnceLock<AudioErrorMode> = OnceLock::new();
+
+/// Raised when the game plays an audio file that doesn't exist, like RGSS does.
+static ENOENT: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    let errno: RModule = ruby
+        .class_object()
+        .const_get("Errno")
+        .expect("Errno is not defined");
+    errno
+        .const_get("ENOENT")
+        .expect("Errno::ENOENT is not defined")
+});
+
+/// Raises audio errors in Ruby, or just prints them, depending on the config.
+pub(crate) fn check<T: Default>(result: Result<T, AudioError>) -> Result<T, magnus::Error> {
+    let error = match result {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+
+    match ERROR_MODE.get().copied().unwrap_or_default() {
+        AudioErrorMode::Raise => {
+            let ruby = magnus::Ruby::get().expect("audio functions are only called from ruby");
+            let class = match &error {
+                AudioError::NotFound(_) => ruby.get_inner(&ENOENT),
+                _ => magnus::exception::runtime_error(),
+            };
+            Err(magnus::Error::new(class, error.to_string()))
+        }
+        AudioErrorMode::Warn => {
+            eprintln!("warning: {error}");
+            Ok(T::default())
+        }
+    }
+}
 
-        let (path,): (String,) = args.required;
-        let (volume, pitch) = args.optional;
+/// Converts a start position in seconds (as RGSS3 uses) to a [`Duration`], treating invalid positions as the start of the track.
+fn start_position(pos: Option<f64>) -> Duration {
+    pos.and_then(|pos| Duration::try_from_secs_f64(pos).ok())
+        .unwrap_or(Duration::ZERO)
+}
 
-        get_audio()
-            .read()
-            .bgm_play(path, volume.unwrap_or(100), pitch.unwrap_or(100));
-    }
-    #[cfg(feature = "modshot")]
-    {
-        let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+fn setup_midi() -> Result<(), magnus::Error> {
+    check(get_audio().read().setup_midi())
+}
 
-        let (path,): (String,) = args.required;
-        let (volume, pitch, pos, nofade): (Option<u32>, Option<u32>, Option<i32>, Option<bool>) =
-            args.optional;
+fn bgm_play(args: &[Value]) -> Result<(), magnus::Error> {
+    // RGSS3 adds a start position, and modshot adds nofade after that
+    let max_optional = if cfg!(feature = "modshot") {
+        4
+    } else if get_rgss_version() >= RgssVersion::Rgss3 {
+        3
+    } else {
+        2
+    };
+    magnus::scan_args::check_arity(args.len(), 1..=1 + max_optional)?;
 
-        get_audio()
-            .read()
-            .bgm_play(path, volume.unwrap_or(100), pitch.unwrap_or(100));
-    }
+    let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
 
-    Ok(())
+    let (path,): (String,) = args.required;
+    let (volume, pitch, pos, nofade): (Option<u32>, Option<u32>, Option<f64>, Option<bool>) =
+        args.optional;
+
+    check(get_audio().read().channel_play_from(
+        AudioChannel::Bgm,
+        path,
+        volume.unwrap_or(100),
+        pitch.unwrap_or(100),
+        start_position(pos),
+        !nofade.unwrap_or(false),
+    ))
 }
 
-fn bgm_stop() {
-    get_audio().read().bgm_stop()
+fn bgm_stop() -> Result<(), magnus::Error> {
+    check(get_audio().read().bgm_stop())
 }
 
-fn bgm_fade(time: u32) {
-    get_audio().read().bgm_fade(time)
+fn bgm_fade(time: u32) -> Result<(), magnus::Error> {
+    check(get_audio().read().bgm_fade(time))
 }
 
 fn bgs_play(args: &[Value]) -> Result<(), magnus::Error> {
+    // RGSS3 adds a start position
+    let max_optional = if get_rgss_version() >= RgssVersion::Rgss3 {
+        3
+    } else {
+        2
+    };
+    magnus::scan_args::check_arity(args.len(), 1..=1 + max_optional)?;
+
     let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
 
     let (path,): (String,) = args.required;
-    let (volume, pitch) = args.optional;
+    let (volume, pitch, pos): (Option<u32>, Option<u32>, Option<f64>) = args.optional;
+
+    check(get_audio().read().channel_play_from(
+        AudioChannel::Bgs,
+        path,
+        volume.unwrap_or(100),
+        pitch.unwrap_or(100),
+        start_position(pos),
+        true,
+    ))
+}
 
-    get_audio()
-        .read()
-        .bgs_play(path, volume.unwrap_or(100), pitch.unwrap_or(100));
+fn bgm_pos() -> Result<f64, magnus::Error> {
+    check(get_audio().read().bgm_pos()).map(|pos| pos.as_secs_f64())
+}
 
-    Ok(())
+fn bgs_stop() -> Result<(), magnus::Error> {
+    check(get_audio().read().bgs_stop())
 }
 
-fn bgs_stop() {
-    get_audio().read().bgs_stop()
+fn bgs_fade(time: u32) -> Result<(), magnus::Error> {
+    check(get_audio().read().bgs_fade(time))
 }
 
-fn bgs_fade(time: u32) {
-    get_audio().read().bgs_fade(time)
+fn bgs_pos() -> Result<f64, magnus::Error> {
+    check(get_audio().read().bgs_pos()).map(|pos| pos.as_secs_f64())
 }
 
 fn me_play(args: &[Value]) -> Result<(), magnus::Error> {
@@ -93,19 +165,19 @@ fn me_play(args: &[Value]) -> Result<(), magnus::Error> {
     let (path,): (String,) = args.required;
     let (volume, pitch) = args.optional;
 
-    get_audio()
-        .read()
-        .me_play(path, volume.unwrap_or(100), pitch.unwrap_or(100));
-
-    Ok(())
+    check(
+        get_audio()
+            .read()
+            .me_play(path, volume.unwrap_or(100), pitch.unwrap_or(100)),
+    )
 }
 
-fn me_stop() {
-    get_audio().read().me_stop()
+fn me_stop() -> Result<(), magnus::Error> {
+    check(get_audio().read().me_stop())
 }
 
-fn me_fade(time: u32) {
-    get_audio().read().me_fade(time)
+fn me_fade(time: u32) -> Result<(), magnus::Error> {
+    check(get_audio().read().me_fade(time))
 }
 
 fn se_play(args: &[Value]) -> Result<(), magnus::Error> {
@@ -114,59 +186,210 @@ fn se_play(args: &[Value]) -> Result<(), magnus::Error> {
     let (path,): (String,) = args.required;
     let (volume, pitch) = args.optional;
 
-    get_audio()
-        .read()
-        .se_play(path, volume.unwrap_or(100), pitch.unwrap_or(100));
+    check(
+        get_audio()
+            .read()
+            .se_play(path, volume.unwrap_or(100), pitch.unwrap_or(100)),
+    )
+}
+
+fn se_stop() -> Result<(), magnus::Error> {
+    check(get_audio().read().se_stop())
+}
 
-    Ok(())
+/// Volume sliders (0-100), like mkxp-z's `Audio.bgm_volume`. These are saved between sessions.
+fn set_volume(category: VolumeCategory, volume: i32) -> Result<(), magnus::Error> {
+    check(
+        get_audio()
+            .read()
+            .set_volume(category, volume.max(0) as u32),
+    )
+}
+
+fn master_volume() -> u32 {
+    get_audio().read().volume(VolumeCategory::Master)
+}
+
+fn set_master_volume(volume: i32) -> Result<(), magnus::Error> {
+    set_volume(VolumeCategory::Master, volume)
+}
+
+fn bgm_volume() -> u32 {
+    get_audio().read().volume(VolumeCategory::Bgm)
+}
+
+fn set_bgm_volume(volume: i32) -> Result<(), magnus::Error> {
+    set_volume(VolumeCategory::Bgm, volume)
 }
 
-fn se_stop() {
-    get_audio().read().se_stop()
+fn bgs_volume() -> u32 {
+    get_audio().read().volume(VolumeCategory::Bgs)
+}
+
+fn set_bgs_volume(volume: i32) -> Result<(), magnus::Error> {
+    set_volume(VolumeCategory::Bgs, volume)
+}
+
+fn me_volume() -> u32 {
+    get_audio().read().volume(VolumeCategory::Me)
+}
+
+fn set_me_volume(volume: i32) -> Result<(), magnus::Error> {
+    set_volume(VolumeCategory::Me, volume)
+}
+
+fn se_volume() -> u32 {
+    get_audio().read().volume(VolumeCategory::Se)
+}
+
+fn set_se_volume(volume: i32) -> Result<(), magnus::Error> {
+    set_volume(VolumeCategory::Se, volume)
+}
+
+/// Seconds, like modshot.
+#[cfg(feature = "modshot")]
+const DEFAULT_CROSSFADE_TIME: f64 = 2.0;
+
+/// `*_crossfade(filename, time = 2, volume = 100, pitch = 100, pos = 0)`
+#[cfg(feature = "modshot")]
+fn crossfade(channel: AudioChannel, args: &[Value]) -> Result<(), magnus::Error> {
+    let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+
+    let (path,): (String,) = args.required;
+    let (time, volume, pitch, pos): (Option<f64>, Option<u32>, Option<u32>, Option<f64>) =
+        args.optional;
+
+    let time = time.unwrap_or(DEFAULT_CROSSFADE_TIME);
+    check(get_audio().read().channel_crossfade(
+        channel,
+        path,
+        volume.unwrap_or(100),
+        pitch.unwrap_or(100),
+        start_position(pos),
+        (time * 1000.0) as u32,
+    ))
+}
+
+/// `*ch_play(channel, filename, volume = 100, pitch = 100, pos = 0)`
+#[cfg(feature = "modshot")]
+fn numbered_play(
+    channel: impl Fn(u32) -> AudioChannel,
+    args: &[Value],
+) -> Result<(), magnus::Error> {
+    let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+
+    let (channel_id, path): (u32, String) = args.required;
+    let (volume, pitch, pos): (Option<u32>, Option<u32>, Option<f64>) = args.optional;
+
+    check(get_audio().read().channel_play_from(
+        channel(channel_id),
+        path,
+        volume.unwrap_or(100),
+        pitch.unwrap_or(100),
+        start_position(pos),
+        false,
+    ))
+}
+
+/// `*ch_crossfade(channel, filename, time = 2, volume = 100, pitch = 100, pos = 0)`
+#[cfg(feature = "modshot")]
+fn numbered_crossfade(
+    channel: impl Fn(u32) -> AudioChannel,
+    args: &[Value],
+) -> Result<(), magnus::Error> {
+    magnus::scan_args::check_arity(args.len(), 2..=6)?;
+    let channel_id = u32::try_convert(args[0])?;
+
+    crossfade(channel(channel_id), &args[1..])
+}
+
+#[cfg(feature = "modshot")]
+fn bgm_crossfade(args: &[Value]) -> Result<(), magnus::Error> {
+    crossfade(AudioChannel::Bgm, args)
+}
+
+#[cfg(feature = "modshot")]
+fn bgs_crossfade(args: &[Value]) -> Result<(), magnus::Error> {
+    crossfade(AudioChannel::Bgs, args)
+}
+
+#[cfg(feature = "modshot")]
+fn me_crossfade(args: &[Value]) -> Result<(), magnus::Error> {
+    crossfade(AudioChannel::Me, args)
+}
+
+#[cfg(feature = "modshot")]
+fn ch_play(args: &[Value]) -> Result<(), magnus::Error> {
+    numbered_play(AudioChannel::Numbered, args)
 }
 
 #[cfg(feature = "modshot")]
-fn bgm_crossfade(args: &[Value]) {
-    // TODO
+fn ch_crossfade(args: &[Value]) -> Result<(), magnus::Error> {
+    numbered_crossfade(AudioChannel::Numbered, args)
 }
 
 #[cfg(feature = "modshot")]
-fn bgs_crossfade(args: &[Value]) {
-    // TODO
+fn ch_stop(channel: u32) -> Result<(), magnus::Error> {
+    check(
+        get_audio()
+            .read()
+            .channel_stop(AudioChannel::Numbered(channel)),
+    )
 }
 
 #[cfg(feature = "modshot")]
-fn me_crossfade(args: &[Value]) {
-    // TODO
+fn ch_fade(channel: u32, time: u32) -> Result<(), magnus::Error> {
+    check(
+        get_audio()
+            .read()
+            .channel_fade(AudioChannel::Numbered(channel), time),
+    )
 }
 
 #[cfg(feature = "modshot")]
-fn ch_play(args: &[Value]) {
-    // TODO
+fn lch_play(args: &[Value]) -> Result<(), magnus::Error> {
+    numbered_play(AudioChannel::LoopingNumbered, args)
 }
 
 #[cfg(feature = "modshot")]
-fn ch_crossfade(args: &[Value]) {
-    // TODO
+fn lch_crossfade(args: &[Value]) -> Result<(), magnus::Error> {
+    numbered_crossfade(AudioChannel::LoopingNumbered, args)
 }
 
 #[cfg(feature = "modshot")]
-fn lch_play(args: &[Value]) {
-    // TODO
+fn lch_stop(channel: u32) -> Result<(), magnus::Error> {
+    check(
+        get_audio()
+            .read()
+            .channel_stop(AudioChannel::LoopingNumbered(channel)),
+    )
 }
 
 #[cfg(feature = "modshot")]
-fn lch_crossfade(args: &[Value]) {
-    // TODO
+fn lch_fade(channel: u32, time: u32) -> Result<(), magnus::Error> {
+    check(
+        get_audio()
+            .read()
+            .channel_fade(AudioChannel::LoopingNumbered(channel), time),
+    )
 }
 
-pub fn bind(ruby: &magnus::Ruby, audio: librgss::Audio) -> Result<(), magnus::Error> {
+pub fn bind(
+    ruby: &magnus::Ruby,
+    audio: librgss::Audio,
+    error_mode: AudioErrorMode,
+) -> Result<(), magnus::Error> {
     let module = ruby.define_module("Audio")?;
 
     // panic if audio is set! this should not *ever* happen
     if AUDIO.set(RwLock::new(audio)).is_err() {
         panic!("audio static already set! this is not supposed to happen")
     }
+    let _ = ERROR_MODE.set(error_mode);
+
+    if get_rgss_version() >= RgssVersion::Rgss2 {
+        module.define_module_function("setup_midi", function!(setup_midi, 0))?;
+    }
 
     module.define_module_function("bgm_play", function!(bgm_play, -1))?;
     module.define_module_function("bgm_stop", function!(bgm_stop, 0))?;
@@ -183,6 +406,22 @@ pub fn bind(ruby: &magnus::Ruby, audio: librgss::Audio) -> Result<(), magnus::Er
     module.define_module_function("se_play", function!(se_play, -1))?;
     module.define_module_function("se_stop", function!(se_stop, 0))?;
 
+    module.define_module_function("master_volume", function!(master_volume, 0))?;
+    module.define_module_function("master_volume=", function!(set_master_volume, 1))?;
+    module.define_module_function("bgm_volume", function!(bgm_volume, 0))?;
+    module.define_module_function("bgm_volume=", function!(set_bgm_volume, 1))?;
+    module.define_module_function("bgs_volume", function!(bgs_volume, 0))?;
+    module.define_module_function("bgs_volume=", function!(set_bgs_volume, 1))?;
+    module.define_module_function("me_volume", function!(me_volume, 0))?;
+    module.define_module_function("me_volume=", function!(set_me_volume, 1))?;
+    module.define_module_function("se_volume", function!(se_volume, 0))?;
+    module.define_module_function("se_volume=", function!(set_se_volume, 1))?;
+
+    if get_rgss_version() >= RgssVersion::Rgss3 || cfg!(feature = "modshot") {
+        module.define_module_function("bgm_pos", function!(bgm_pos, 0))?;
+        module.define_module_function("bgs_pos", function!(bgs_pos, 0))?;
+    }
+
     #[cfg(feature = "modshot")]
     {
         module.define_module_function("bgm_crossfade", function!(bgm_crossfade, -1))?;
@@ -191,8 +430,13 @@ pub fn bind(ruby: &magnus::Ruby, audio: librgss::Audio) -> Result<(), magnus::Er
 
         module.define_module_function("ch_play", function!(ch_play, -1))?;
         module.define_module_function("ch_crossfade", function!(ch_crossfade, -1))?;
+        module.define_module_function("ch_stop", function!(ch_stop, 1))?;
+        module.define_module_function("ch_fade", function!(ch_fade, 2))?;
+
         module.define_module_function("lch_play", function!(lch_play, -1))?;
         module.define_module_function("lch_crossfade", function!(lch_crossfade, -1))?;
+        module.define_module_function("lch_stop", function!(lch_stop, 1))?;
+        module.define_module_function("lch_fade", function!(lch_fade, 2))?;
     }
 
     Ok(())
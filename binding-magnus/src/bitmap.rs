@@ -20,6 +20,7 @@ use magnus::{function, method, typed_data::Obj, Class, Module, Object, TryConver
 
 use crate::{
     data::Rect,
+    error::disposed_bitmap,
     font::{get_fonts, Font},
     get_arenas,
     graphics::get_graphics,
@@ -44,8 +45,11 @@ impl Provider<librgss::Font> for BitmapFontProvider {
         F: FnOnce(&librgss::Font) -> R,
     {
         let arenas = get_arenas().read();
-        let font = self.0.font(&arenas);
-        f(font)
+        match self.0.font(&arenas) {
+            Some(font) => f(font),
+            // fonts have no way to raise, so a disposed bitmap's font reads as the default
+            None => f(&librgss::Font::default(&get_fonts().read())),
+        }
     }
 
     fn provide_mut<F, R>(&mut self, f: F) -> R
@@ -53,8 +57,11 @@ impl Provider<librgss::Font> for BitmapFontProvider {
         F: FnOnce(&mut librgss::Font) -> R,
     {
         let mut arenas = get_arenas().write();
-        let font = self.0.font_mut(&mut arenas);
-        f(font)
+        match self.0.font_mut(&mut arenas) {
+            Some(font) => f(font),
+            // and changes to it go nowhere
+            None => f(&mut librgss::Font::default(&get_fonts().read())),
+        }
     }
 }
 
@@ -89,21 +96,25 @@ impl Bitmap {
         Ok(())
     }
 
-    fn width(&self) -> u32 {
+    fn width(&self) -> Result<u32, magnus::Error> {
         let arenas = get_arenas().read();
-        self.0.load().width(&arenas)
+        self.0.load().width(&arenas).ok_or_else(disposed_bitmap)
     }
 
-    fn height(&self) -> u32 {
+    fn height(&self) -> Result<u32, magnus::Error> {
         let arenas = get_arenas().read();
-        self.0.load().width(&arenas)
+        self.0.load().height(&arenas).ok_or_else(disposed_bitmap)
     }
 
-    fn text_size(&self, text: String) -> Rect {
+    fn text_size(&self, text: String) -> Result<Rect, magnus::Error> {
         let arenas = get_arenas().read();
         let mut fonts = get_fonts().write();
-        let rect = self.0.load().text_size(&arenas, &mut fonts, &text);
-        Rect::from_val(rect)
+        let rect = self
+            .0
+            .load()
+            .text_size(&arenas, &mut fonts, &text)
+            .ok_or_else(disposed_bitmap)?;
+        Ok(Rect::from_val(rect))
     }
 
     fn draw_text(&self, args: &[Value]) -> Result<(), magnus::Error> {
@@ -152,8 +163,13 @@ impl Bitmap {
         // RGSS props do not take references, the actually take values! (kind of)
         // See https://github.com/Ancurio/mkxp/commit/f8c26fc515cb4fb6b24b766889d4b0b0a3c12a26#diff-dbf082db65931f45df274de8694f3df0ecbb77952084bfb3565e0bb184489160
         let mut arenas = get_arenas().write();
+        let bitmap_font = self
+            .0
+            .load()
+            .font_mut(&mut arenas)
+            .ok_or_else(disposed_bitmap)?;
         font.0.read().provide(|f| {
-            *self.0.load().font_mut(&mut arenas) = f.clone();
+            *bitmap_font = f.clone();
         });
         Ok(())
     }
@@ -163,7 +179,8 @@ impl Bitmap {
     }
 
     fn disposed(&self) -> bool {
-        false
+        let arenas = get_arenas().read();
+        self.0.load().disposed(&arenas)
     }
 }
 
@@ -16,41 +16,222 @@
 // along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
 use color_eyre::Section;
-use magnus::{error::ErrorType, value::ReprValue, Class};
+use magnus::{
+    error::ErrorType,
+    exception::ExceptionClass,
+    value::{Lazy, ReprValue},
+    Class, Module,
+};
+
+/// Where uncaught script errors are logged to, relative to the game directory.
+const CRASH_LOG_PATH: &str = "crash.log";
+
+/// Raised from `Input.update` and `Graphics.update` when the player presses F12.
+pub static RGSS_RESET: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    ruby.class_object()
+        .const_get("RGSSReset")
+        .expect("RGSSReset is not defined")
+});
+
+pub fn rgss_reset(ruby: &magnus::Ruby) -> magnus::Error {
+    magnus::Error::new(ruby.get_inner(&RGSS_RESET), "")
+}
+
+pub static RGSS_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    ruby.class_object()
+        .const_get("RGSSError")
+        .expect("RGSSError is not defined")
+});
+
+/// Raised when using a bitmap that has been disposed, including by a soft reset.
+pub fn disposed_bitmap() -> magnus::Error {
+    let ruby = magnus::Ruby::get().expect("bitmaps are only used from ruby");
+    magnus::Error::new(ruby.get_inner(&RGSS_ERROR), "disposed bitmap")
+}
 
 pub fn magnus_to_eyre(value: magnus::Error) -> color_eyre::Report {
+    let exception = error_to_exception(value);
+
+    // we're pretty quckily converting this to an owned value so this is ok.
+    let class_name = unsafe { exception.classname() };
+    let mut report = color_eyre::Report::msg(format!("{class_name}: {exception}"));
+    // get rid of class_name so we don't have to worry about it.
+    drop(class_name);
+
+    let backtrace = exception_backtrace(exception);
+    if !backtrace.is_empty() {
+        report = report.note("ruby backtrace:");
+        for line in backtrace {
+            report = report.note(line);
+        }
+    }
+
+    report
+}
+
+fn error_to_exception(value: magnus::Error) -> magnus::Value {
     let handle = unsafe { magnus::Ruby::get_unchecked() };
 
-    let exception = match value.error_type() {
+    match value.error_type() {
         ErrorType::Jump(_) => unimplemented!(),
         ErrorType::Error(class, msg) => class
             .new_instance((handle.str_new(msg.as_ref()),))
             .expect("fatal error converting magnus error"),
         ErrorType::Exception(exception) => *exception,
+    }
+}
+
+fn exception_backtrace(exception: magnus::Value) -> Vec<String> {
+    let backtrace: Option<magnus::RArray> = exception.funcall("backtrace", ()).ok().flatten();
+    let Some(backtrace) = backtrace else {
+        return vec![];
     };
 
-    // we're pretty quckily converting this to an owned value so this is ok.
-    let class_name = unsafe { exception.classname() };
-    let mut report = color_eyre::Report::msg(format!("{class_name}: {exception}"));
-    // get rid of class_name so we don't have to worry about it.
-    drop(class_name);
+    backtrace
+        .each()
+        .map_while(Result::ok)
+        .map(|line| line.to_string())
+        .collect()
+}
 
-    let backtrace: Option<magnus::RArray> = exception.funcall("backtrace", ()).ok().flatten();
-    if let Some(backtrace) = backtrace {
-        report = report.note("ruby backtrace:");
-        for line in backtrace.each() {
-            let Ok(line) = line else {
-                break;
-            };
-            report = report.note(line.to_string());
+/// An uncaught exception raised by a script, formatted the way RGSS reports it.
+#[derive(Debug)]
+pub struct ScriptError {
+    /// The script section and line the exception was raised from, if it came from a script.
+    pub location: Option<(String, u32)>,
+    pub class_name: String,
+    pub message: String,
+    pub backtrace: Vec<String>,
+}
+
+impl ScriptError {
+    pub fn new(value: magnus::Error) -> Self {
+        let exception = error_to_exception(value);
+
+        let class_name = unsafe { exception.classname() }.into_owned();
+        let message = exception.to_string();
+        let backtrace = exception_backtrace(exception);
+
+        // scripts are evaluated with their section name as the file name, so the first line of the backtrace
+        // tells us which section the exception came from
+        let location = backtrace
+            .first()
+            .map(String::as_str)
+            .and_then(parse_backtrace_line);
+
+        Self {
+            location,
+            class_name,
+            message,
+            backtrace,
         }
     }
 
-    report
+    /// Writes the report to the crash log, and shows it to the player unless running headless.
+    ///
+    /// Failing to write the crash log is not fatal, as the caller still returns the error (which is printed to stderr).
+    pub fn report(&self, config: &librgss::Config) {
+        if let Err(e) = std::fs::write(CRASH_LOG_PATH, self.to_string()) {
+            eprintln!("failed to write {CRASH_LOG_PATH}: {e}");
+        }
+
+        if !config.headless {
+            rfd::MessageDialog::new()
+                .set_buttons(rfd::MessageButtons::Ok)
+                .set_level(rfd::MessageLevel::Error)
+                .set_description(self.summary())
+                .set_title("Sapphire")
+                .show();
+        }
+    }
+
+    /// The short message RGSS shows in its error dialog.
+    pub fn summary(&self) -> String {
+        let Self {
+            class_name,
+            message,
+            ..
+        } = self;
+        match &self.location {
+            Some((section, line)) => {
+                format!("Script '{section}' line {line}: {class_name} occurred.\n\n{message}")
+            }
+            None => format!("{class_name} occurred.\n\n{message}"),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        if !self.backtrace.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Backtrace:")?;
+            for line in self.backtrace.iter() {
+                writeln!(f, "\t{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Splits a ruby backtrace line (like `Scene_Map:12:in 'update'`) into its file and line number.
+///
+/// Section names may contain colons, so we look for the first `:<line>` that is followed by `:` or the end of the line.
+fn parse_backtrace_line(line: &str) -> Option<(String, u32)> {
+    line.match_indices(':').find_map(|(index, _)| {
+        let (file, rest) = line.split_at(index);
+        let rest = &rest[1..];
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let suffix = &rest[digits_end..];
+        if digits_end == 0 || !(suffix.is_empty() || suffix.starts_with(':')) {
+            return None;
+        }
+
+        let line = rest[..digits_end].parse().ok()?;
+        Some((file.to_string(), line))
+    })
 }
 
 pub fn bind(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
     let class = ruby.define_class("RGSSError", ruby.exception_standard_error().as_r_class())?;
+    // RGSSReset bypasses `rescue => e`, so scripts can't accidentally swallow it
+    ruby.define_class("RGSSReset", ruby.exception_exception().as_r_class())?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbered_sections() {
+        assert_eq!(
+            parse_backtrace_line("{0001}:12:in 'foo'"),
+            Some(("{0001}".to_string(), 12))
+        );
+        assert_eq!(
+            parse_backtrace_line("{0042}:3"),
+            Some(("{0042}".to_string(), 3))
+        );
+    }
+
+    #[test]
+    fn section_names_with_colons() {
+        assert_eq!(
+            parse_backtrace_line("Battle: Actions:7:in `update'"),
+            Some(("Battle: Actions".to_string(), 7))
+        );
+    }
+
+    #[test]
+    fn lines_without_a_line_number() {
+        assert_eq!(parse_backtrace_line("(eval)"), None);
+        assert_eq!(parse_backtrace_line("Main:in 'foo'"), None);
+    }
+}
@@ -193,34 +193,28 @@ impl Font {
         fonts.default.color = color;
     }
 
-    #[cfg(feature = "rgss2")]
     fn default_shadow() -> bool {
         get_fonts().read().default.shadow
     }
 
-    #[cfg(feature = "rgss2")]
     fn set_default_shadow(shadow: bool) {
         get_fonts().write().default.shadow = shadow
     }
 
-    #[cfg(feature = "rgss3")]
     fn default_outline(class: magnus::RClass) -> Result<magnus::Value, magnus::Error> {
         class.ivar_get("default_outline")
     }
 
-    #[cfg(feature = "rgss3")]
     fn set_default_outline(color: &Color) {
         let mut fonts = get_fonts().write();
         let color = color.as_color();
         fonts.default.outline = color;
     }
 
-    #[cfg(feature = "rgss3")]
     fn default_out_color(class: magnus::RClass) -> Result<magnus::Value, magnus::Error> {
         class.ivar_get("default_out_color")
     }
 
-    #[cfg(feature = "rgss3")]
     fn set_default_out_color(color: &Color) {
         let mut fonts = get_fonts().write();
         let color = color.as_color();
@@ -228,7 +222,11 @@ impl Font {
     }
 }
 
-pub fn bind(ruby: &magnus::Ruby, fonts: librgss::Fonts) -> Result<(), magnus::Error> {
+pub fn bind(
+    ruby: &magnus::Ruby,
+    fonts: librgss::Fonts,
+    rgss_version: librgss::RgssVersion,
+) -> Result<(), magnus::Error> {
     let class = ruby.define_class("Font", ruby.class_object())?;
 
     // panic if graphic is set! this should not *ever* happen
@@ -261,14 +259,13 @@ pub fn bind(ruby: &magnus::Ruby, fonts: librgss::Fonts) -> Result<(), magnus::Er
     class.define_singleton_method("default_color", method!(Font::default_color, 0))?;
     class.define_singleton_method("default_color=", function!(Font::set_default_color, 1))?;
 
-    #[cfg(feature = "rgss2")]
-    {
+    // modshot exposes the newer font properties regardless of version
+    if rgss_version >= librgss::RgssVersion::Rgss2 || cfg!(feature = "modshot") {
         class.define_singleton_method("default_shadow", function!(Font::default_shadow, 0))?;
         class.define_singleton_method("default_shadow=", function!(Font::set_default_shadow, 1))?;
     }
 
-    #[cfg(feature = "rgss3")]
-    {
+    if rgss_version >= librgss::RgssVersion::Rgss3 || cfg!(feature = "modshot") {
         class.define_singleton_method("default_outline", method!(Font::default_outline, 0))?;
         class
             .define_singleton_method("default_outline=", function!(Font::set_default_outline, 1))?;
@@ -17,9 +17,12 @@
 
 use magnus::{function, Value};
 use parking_lot::RwLock;
-use std::sync::OnceLock;
+use std::{
+    sync::{Once, OnceLock},
+    time::Duration,
+};
 
-use crate::get_arenas;
+use crate::{audio::get_audio, get_arenas, input::get_input};
 
 // FIXME find a way around using a static
 pub(crate) static GRAPHICS: OnceLock<RwLock<librgss::Graphics>> = OnceLock::new();
@@ -31,10 +34,30 @@ pub fn get_graphics() -> &'static RwLock<librgss::Graphics> {
         .expect("graphics static not set! please report how you encountered this crash")
 }
 
-fn update() {
-    let mut graphics = get_graphics().write();
-    let arenas = get_arenas().read();
-    graphics.update(&arenas);
+fn update(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let framerate = {
+        let mut graphics = get_graphics().write();
+        let arenas = get_arenas().read();
+        graphics.update(&arenas);
+        graphics.framerate
+    };
+    // keeps offline audio in step with the game
+    let result = get_audio()
+        .read()
+        .advance(Duration::from_secs(1) / framerate.max(1) as u32);
+    if let Err(error) = result {
+        // the game can carry on without audio, so this is only reported once
+        static REPORTED: Once = Once::new();
+        REPORTED.call_once(|| eprintln!("failed to advance audio: {error}"));
+    }
+    // tracks that turned out to be broken once the audio thread got to them
+    crate::audio::check(get_audio().read().take_error())?;
+
+    if get_input().write().take_reset_request() {
+        Err(crate::error::rgss_reset(ruby))
+    } else {
+        Ok(())
+    }
 }
 
 fn fullscreen() -> bool {
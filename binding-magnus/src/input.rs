@@ -15,12 +15,14 @@
 // You should have received a copy of the GNU General Public License
 // along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
-use magnus::{function, Module};
+use magnus::{function, Module, Symbol, TryConvert, Value};
 
 use parking_lot::RwLock;
 use std::sync::OnceLock;
 
-use librgss::{Button, KeyBind};
+use librgss::{Button, KeyBind, NamedButton, RgssVersion};
+
+use crate::{get_rgss_version, graphics::get_graphics};
 
 // FIXME find a way around using a static
 pub(crate) static INPUT: OnceLock<RwLock<librgss::Input>> = OnceLock::new();
@@ -28,6 +30,61 @@ pub(crate) static INPUT: OnceLock<RwLock<librgss::Input>> = OnceLock::new();
 #[magnus::wrap(class = "Input::Button", size, free_immediately, frozen_shareable)]
 pub(crate) struct RButton(Button);
 
+/// Every constant defined under `Input`, and the button it refers to.
+///
+/// RGSS1 and RGSS2 use opaque values for these, while RGSS3 uses symbols named after the constant.
+const BUTTON_CONSTANTS: &[(&str, Button)] = &[
+    ("DOWN", Button::KeyBind(KeyBind::Down)),
+    ("LEFT", Button::KeyBind(KeyBind::Left)),
+    ("RIGHT", Button::KeyBind(KeyBind::Right)),
+    ("UP", Button::KeyBind(KeyBind::Up)),
+    // A is dash, B is cancel and C is confirm in the RPG scripts
+    ("A", Button::KeyBind(KeyBind::Run)),
+    ("B", Button::KeyBind(KeyBind::Cancel)),
+    ("C", Button::KeyBind(KeyBind::Action)),
+    ("X", Button::KeyBind(KeyBind::Menu)),
+    ("Y", Button::KeyBind(KeyBind::Items)),
+    ("Z", Button::KeyBind(KeyBind::Deactivate)),
+    ("L", Button::KeyBind(KeyBind::L)),
+    ("R", Button::KeyBind(KeyBind::R)),
+    ("SHIFT", Button::Named(NamedButton::Shift)),
+    ("CTRL", Button::Named(NamedButton::Ctrl)),
+    ("ALT", Button::Named(NamedButton::Alt)),
+    ("F5", Button::Named(NamedButton::F5)),
+    ("F6", Button::Named(NamedButton::F6)),
+    ("F7", Button::Named(NamedButton::F7)),
+    ("F8", Button::Named(NamedButton::F8)),
+    // the RPG scripts open the debug scene when F9 is pressed and $DEBUG is set
+    ("F9", Button::Named(NamedButton::F9)),
+    // mkxp-z's mouse buttons
+    ("MOUSELEFT", Button::Named(NamedButton::MouseLeft)),
+    ("MOUSEMIDDLE", Button::Named(NamedButton::MouseMiddle)),
+    ("MOUSERIGHT", Button::Named(NamedButton::MouseRight)),
+    // not RGSS constants, but ModShot's names for C and B, which OneShot's scripts use
+    ("ACTION", Button::KeyBind(KeyBind::Action)),
+    ("CANCEL", Button::KeyBind(KeyBind::Cancel)),
+];
+
+fn button_from_value(value: Value) -> Result<Button, magnus::Error> {
+    if let Ok(button) = <&RButton>::try_convert(value) {
+        return Ok(button.0);
+    }
+
+    let ruby = magnus::Ruby::get_with(value);
+    let symbol = Symbol::try_convert(value)?;
+    let name = symbol.name()?;
+    BUTTON_CONSTANTS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, button)| *button)
+        .ok_or_else(|| {
+            magnus::Error::new(
+                ruby.exception_arg_error(),
+                format!("unknown button :{name}"),
+            )
+        })
+}
+
 #[inline(always)]
 pub fn get_input() -> &'static RwLock<librgss::Input> {
     INPUT
@@ -35,30 +92,101 @@ pub fn get_input() -> &'static RwLock<librgss::Input> {
         .expect("input static not set! please report how you encountered this crash")
 }
 
-fn update() -> Result<(), magnus::Error> {
+fn update(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
     let mut input = get_input().write();
     input.update();
 
     if input.exited() {
         Err(magnus::Error::new(magnus::exception::system_exit(), " "))
+    } else if input.take_reset_request() {
+        Err(crate::error::rgss_reset(ruby))
     } else {
         Ok(())
     }
 }
 
-fn trigger(button: &RButton) -> bool {
+fn trigger(button: Value) -> Result<bool, magnus::Error> {
+    let button = button_from_value(button)?;
     let input = get_input().read();
-    input.triggered(button.0)
+    Ok(input.triggered(button))
 }
 
-fn press(button: &RButton) -> bool {
+fn press(button: Value) -> Result<bool, magnus::Error> {
+    let button = button_from_value(button)?;
     let input = get_input().read();
-    input.pressed(button.0)
+    Ok(input.pressed(button))
 }
 
-fn repeat(button: &RButton) -> bool {
+fn repeat(button: Value) -> Result<bool, magnus::Error> {
+    let button = button_from_value(button)?;
     let input = get_input().read();
-    input.repeat(button.0)
+    Ok(input.repeat(button))
+}
+
+fn simulate_press(args: &[Value]) -> Result<(), magnus::Error> {
+    let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+    let (button,): (Value,) = args.required;
+    let (frames, delay): (Option<u32>, Option<u32>) = args.optional;
+
+    let button = button_from_value(button)?;
+    get_input()
+        .write()
+        .simulate_press(button, frames.unwrap_or(1), delay.unwrap_or(0));
+    Ok(())
+}
+
+fn simulate_release(args: &[Value]) -> Result<(), magnus::Error> {
+    let args = magnus::scan_args::scan_args::<_, _, (), (), (), ()>(args)?;
+    let (button,): (Value,) = args.required;
+    let (delay,): (Option<u32>,) = args.optional;
+
+    let button = button_from_value(button)?;
+    get_input()
+        .write()
+        .simulate(button, false, delay.unwrap_or(0));
+    Ok(())
+}
+
+fn mouse_x() -> i32 {
+    get_input().read().mouse_position().0
+}
+
+fn mouse_y() -> i32 {
+    get_input().read().mouse_position().1
+}
+
+fn scroll_v() -> f64 {
+    get_input().read().mouse_scroll()
+}
+
+fn raw_key_states() -> Vec<bool> {
+    get_input().read().raw_key_states()
+}
+
+fn text_input() -> bool {
+    get_input().read().text_input()
+}
+
+fn set_text_input(enabled: bool) {
+    get_input().write().set_text_input(enabled);
+    // IMEs would otherwise pop up over the game whenever a key is pressed
+    get_graphics().read().set_ime_allowed(enabled);
+}
+
+fn gets() -> String {
+    get_input().write().take_text()
+}
+
+fn text_composition() -> String {
+    get_input().read().text_composition().to_string()
+}
+
+fn dir4() -> u8 {
+    get_input().read().dir4()
+}
+
+fn dir8() -> u8 {
+    get_input().read().dir8()
 }
 
 pub fn bind(ruby: &magnus::Ruby, input: librgss::Input) -> Result<(), magnus::Error> {
@@ -76,14 +204,36 @@ pub fn bind(ruby: &magnus::Ruby, input: librgss::Input) -> Result<(), magnus::Er
     module.define_module_function("press?", function!(press, 1))?;
     module.define_module_function("repeat?", function!(repeat, 1))?;
 
+    // for test scripts, which have no window to press buttons in
+    module.define_module_function("simulate_press", function!(simulate_press, -1))?;
+    module.define_module_function("simulate_release", function!(simulate_release, -1))?;
+
+    module.define_module_function("dir4", function!(dir4, 0))?;
+    module.define_module_function("dir8", function!(dir8, 0))?;
+
+    module.define_module_function("mouse_x", function!(mouse_x, 0))?;
+    module.define_module_function("mouse_y", function!(mouse_y, 0))?;
+    module.define_module_function("scroll_v", function!(scroll_v, 0))?;
+
+    // mkxp-z's keyboard functions, for name entry and debug consoles
+    module.define_module_function("raw_key_states", function!(raw_key_states, 0))?;
+    module.define_module_function("text_input", function!(text_input, 0))?;
+    module.define_module_function("text_input=", function!(set_text_input, 1))?;
+    module.define_module_function("gets", function!(gets, 0))?;
+    module.define_module_function("text_composition", function!(text_composition, 0))?;
+
     module.const_set("KEY_M", 0)?;
     module.const_set("KEY_E", 0)?;
     module.const_set("KEY_O", 0)?;
     module.const_set("KEY_W", 0)?;
 
-    module.const_set("ACTION", RButton(Button::KeyBind(KeyBind::Action)))?;
-    module.const_set("CANCEL", RButton(Button::KeyBind(KeyBind::Cancel)))?;
-    module.const_set("R", RButton(Button::KeyBind(KeyBind::R)))?;
+    for &(name, button) in BUTTON_CONSTANTS {
+        if get_rgss_version() >= RgssVersion::Rgss3 {
+            module.const_set(name, Symbol::new(name))?;
+        } else {
+            module.const_set(name, RButton(button))?;
+        }
+    }
 
     Ok(())
 }
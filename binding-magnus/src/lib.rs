@@ -1,9 +1,14 @@
 #![forbid(unsafe_op_in_unsafe_fn)]
 
 mod scripts;
-use std::sync::{Arc, OnceLock};
+use std::{
+    io::Read,
+    sync::{Arc, OnceLock},
+};
 
-use magnus::{function, value::ReprValue};
+use color_eyre::Section;
+
+use magnus::{function, value::ReprValue, Module};
 use parking_lot::RwLock;
 use scripts::Script;
 
@@ -44,30 +49,39 @@ pub fn start(
     fonts: librgss::Fonts,
     input: librgss::Input,
     filesystem: Arc<librgss::FileSystem>,
+    config: librgss::Config,
 ) -> std::thread::JoinHandle<color_eyre::Result<()>> {
     // panic if arena is set! this should not *ever* happen
     if ARENAS.set(RwLock::new(arenas)).is_err() {
         panic!("arenas static already set! this is not supposed to happen")
     }
+    if RGSS_VERSION.set(config.rgss_version()).is_err() {
+        panic!("rgss version static already set! this is not supposed to happen")
+    }
 
     std::thread::Builder::new()
         .name("librgss ruby thread".to_string())
         .spawn(move || {
             //? Safety
             //? These bindings don't provide a way to access ruby values *at all* so it's not possible to access ruby values outside of this function call.
-            let result = unsafe { run_ruby_thread(audio, graphics, fonts, input, filesystem) };
+            let result =
+                unsafe { run_ruby_thread(audio, graphics, fonts, input, filesystem, config) };
+            // finish any input recording (or replay) before exiting
+            let finished = input::get_input().write().finish();
             // exit the event loop after we're finished running ruby code (for any reason)
             input::get_input().read().exit();
             // stop audio processing
             // FIXME should we do this here, or in main.rs?
             audio::get_audio().read().stop_processing();
 
-            result
+            // a replay that crashed will have stopped early, so the script error is the one to report
+            result.and(finished)
         })
         .expect("failed to start ruby thread")
 }
 
 static ARENAS: OnceLock<RwLock<librgss::Arenas>> = OnceLock::new();
+static RGSS_VERSION: OnceLock<librgss::RgssVersion> = OnceLock::new();
 
 fn get_arenas() -> &'static RwLock<librgss::Arenas> {
     ARENAS
@@ -75,39 +89,153 @@ fn get_arenas() -> &'static RwLock<librgss::Arenas> {
         .expect("arenas static not set! please report how you encountered this crash")
 }
 
+fn get_rgss_version() -> librgss::RgssVersion {
+    *RGSS_VERSION
+        .get()
+        .expect("rgss version static not set! please report how you encountered this crash")
+}
+
 unsafe fn run_ruby_thread(
     audio: librgss::Audio,
     graphics: librgss::Graphics,
     fonts: librgss::Fonts,
     input: librgss::Input,
     filesystem: Arc<librgss::FileSystem>,
+    config: librgss::Config,
 ) -> color_eyre::Result<()> {
     let ruby = unsafe { magnus::embed::init() };
 
     // It is *really* important that we call this function before doing anyhting else!
     // If any initialization fails, input::get_input() might fail and we will panic.
-    init_bindings(&ruby, audio, graphics, fonts, input, filesystem)
-        .map_err(error::magnus_to_eyre)?;
-
-    rpg::eval(&ruby).map_err(error::magnus_to_eyre)?;
+    init_bindings(
+        &ruby,
+        audio,
+        graphics,
+        fonts,
+        input,
+        filesystem.clone(),
+        &config,
+    )
+    .map_err(error::magnus_to_eyre)?;
+
+    set_launch_globals(&ruby, &config).map_err(error::magnus_to_eyre)?;
+    seed_rng(&ruby).map_err(error::magnus_to_eyre)?;
+
+    rpg::eval(&ruby, config.rgss_version()).map_err(error::magnus_to_eyre)?;
 
     // FIXME should we just use marshal directly from ruby?
-    let script_data = std::fs::read("Data/xScripts.rxdata")?;
+    let scripts_path = config.scripts_path();
+    let mut script_data = vec![];
+    filesystem
+        .read_file(&scripts_path)?
+        .read_to_end(&mut script_data)
+        .with_note(|| format!("while reading scripts from {scripts_path}"))?;
     let scripts: Vec<Script> = alox_48::from_bytes(&script_data)?;
+    let preload_scripts = Script::load_all(&filesystem, &config.preload_scripts)?;
 
     // run all scripts. due to the design of rgss, this will block until script completion
     // if the event loop has exited, the next call to Input::update will raise SystemExit, so this loop will exit
+    loop {
+        // preload scripts run after the RPG module is defined, so they are able to patch it
+        // they are run again after a reset, as the game's scripts will have been re-evaluated over them
+        let scripts = preload_scripts.iter().chain(scripts.iter());
+        match run_scripts(&ruby, scripts, &config)? {
+            ScriptsResult::Finished => break,
+            ScriptsResult::Exited => return Ok(()),
+            ScriptsResult::Reset => soft_reset()?,
+        }
+    }
+
+    let postload_scripts = Script::load_all(&filesystem, &config.postload_scripts)?;
+    run_scripts(&ruby, postload_scripts.iter(), &config)?;
+
+    Ok(())
+}
+
+/// Sets the globals the RPG scripts check to open the debug menu (F9) or start a battle test.
+fn set_launch_globals(ruby: &magnus::Ruby, config: &librgss::Config) -> Result<(), magnus::Error> {
+    let librgss::Config {
+        debug, battle_test, ..
+    } = config;
+    ruby.eval::<magnus::Value>(&format!(
+        "$DEBUG = {debug}\n$TEST = {debug}\n$BTEST = {battle_test}\n"
+    ))?;
+
+    Ok(())
+}
+
+/// Seeds the scripts' random number generator from the input recording, so replays make the same random choices.
+fn seed_rng(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let Some(seed) = input::get_input().read().rng_seed() else {
+        return Ok(());
+    };
+    ruby.module_kernel()
+        .funcall::<_, _, magnus::Value>("srand", (seed,))?;
+
+    Ok(())
+}
+
+enum ScriptsResult {
+    Finished,
+    /// A script raised `SystemExit`. No further scripts should be run.
+    Exited,
+    /// A script raised `RGSSReset`. Scripts should be run again from the start.
+    Reset,
+}
+
+/// Evaluates each script in order, stopping early if a script raises `SystemExit` or `RGSSReset`.
+fn run_scripts<'a>(
+    ruby: &magnus::Ruby,
+    scripts: impl IntoIterator<Item = &'a Script>,
+    config: &librgss::Config,
+) -> color_eyre::Result<ScriptsResult> {
+    // evaluating against TOPLEVEL_BINDING (rather than with ruby.eval) lets us set the file name,
+    // so backtraces point at the script section rather than (eval)
+    let binding: magnus::Value = ruby
+        .class_object()
+        .const_get("TOPLEVEL_BINDING")
+        .map_err(error::magnus_to_eyre)?;
+
     for script in scripts {
-        ruby.script(script.name);
-        let result = ruby.eval::<magnus::Value>(&script.script_text);
+        ruby.script(script.name.as_str());
+        let result = ruby.module_kernel().funcall::<_, _, magnus::Value>(
+            "eval",
+            (
+                script.script_text.as_str(),
+                binding,
+                script.name.as_str(),
+                1,
+            ),
+        );
 
         if let Err(error) = result {
-            if !error.is_kind_of(ruby.exception_system_exit()) {
-                return Err(error::magnus_to_eyre(error));
+            if error.is_kind_of(ruby.exception_system_exit()) {
+                return Ok(ScriptsResult::Exited);
             }
+            if error.is_kind_of(ruby.get_inner(&error::RGSS_RESET)) {
+                return Ok(ScriptsResult::Reset);
+            }
+
+            let error = error::ScriptError::new(error);
+            error.report(config);
+            return Err(error.into());
         }
     }
 
+    Ok(ScriptsResult::Finished)
+}
+
+/// Returns the engine to a clean state after an `RGSSReset`, keeping the process and window around.
+fn soft_reset() -> color_eyre::Result<()> {
+    let mut graphics = graphics::get_graphics().write();
+    let mut arenas = get_arenas().write();
+    graphics.reset(&mut arenas);
+
+    audio::get_audio().read().stop_all()?;
+    input::get_input().write().reset();
+    // resetting input turns text input off
+    graphics.set_ime_allowed(false);
+
     Ok(())
 }
 
@@ -142,8 +270,9 @@ fn init_bindings(
     fonts: librgss::Fonts,
     input: librgss::Input,
     filesystem: Arc<librgss::FileSystem>,
+    config: &librgss::Config,
 ) -> Result<(), magnus::Error> {
-    audio::bind(ruby, audio)?;
+    audio::bind(ruby, audio, config.audio.errors)?;
 
     data::bind(ruby)?;
     error::bind(ruby)?;
@@ -153,7 +282,7 @@ fn init_bindings(
     graphics::bind(ruby, graphics)?;
     bitmap::bind(ruby)?;
     sprite::bind(ruby)?;
-    font::bind(ruby, fonts)?;
+    font::bind(ruby, fonts, get_rgss_version())?;
     plane::bind(ruby)?;
     tilemap::bind(ruby)?;
     viewport::bind(ruby)?;
@@ -15,8 +15,17 @@
 // You should have received a copy of the GNU General Public License
 // along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
-const MODULE_RPG: &str = include_str!("xp.rb");
+const MODULE_RPG_XP: &str = include_str!("xp.rb");
+const MODULE_RPG_VX: &str = include_str!("vx.rb");
+const MODULE_RPG_VXACE: &str = include_str!("vxace.rb");
 
-pub fn eval(ruby: &magnus::Ruby) -> Result<magnus::Value, magnus::Error> {
-    ruby.eval(MODULE_RPG)
+pub fn eval(
+    ruby: &magnus::Ruby,
+    rgss_version: librgss::RgssVersion,
+) -> Result<magnus::Value, magnus::Error> {
+    match rgss_version {
+        librgss::RgssVersion::Rgss1 => ruby.eval(MODULE_RPG_XP),
+        librgss::RgssVersion::Rgss2 => ruby.eval(MODULE_RPG_VX),
+        librgss::RgssVersion::Rgss3 => ruby.eval(MODULE_RPG_VXACE),
+    }
 }
@@ -15,11 +15,47 @@
 // You should have received a copy of the GNU General Public License
 // along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
+use camino::Utf8PathBuf;
+
 pub struct Script {
     pub name: String,
     pub script_text: String,
 }
 
+impl Script {
+    /// Reads a plain ruby script from the filesystem. The path is used as the script name.
+    pub fn load(
+        filesystem: &librgss::FileSystem,
+        path: impl Into<Utf8PathBuf>,
+    ) -> color_eyre::Result<Self> {
+        use color_eyre::Section;
+        use std::io::Read;
+
+        let path = path.into();
+
+        let mut script_text = String::new();
+        filesystem
+            .read_file(&path)
+            .and_then(|mut f| f.read_to_string(&mut script_text).map_err(Into::into))
+            .with_note(|| format!("while reading script {path}"))?;
+
+        Ok(Self {
+            name: path.into_string(),
+            script_text,
+        })
+    }
+
+    pub fn load_all(
+        filesystem: &librgss::FileSystem,
+        paths: &[Utf8PathBuf],
+    ) -> color_eyre::Result<Vec<Self>> {
+        paths
+            .iter()
+            .map(|path| Self::load(filesystem, path))
+            .collect()
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for Script {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -178,6 +178,7 @@ impl Window {
         let window = rb_self.0.load();
 
         match bitmap {
+            Some(b) if b.0.load().disposed(&arenas) => Err(crate::error::disposed_bitmap()),
             Some(b) => {
                 window.set_windowskin(&graphics, &mut arenas, Some(b.0.load()));
                 rb_self.ivar_set("windowskin", b)
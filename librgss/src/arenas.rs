@@ -18,7 +18,7 @@ use slotmap::SlotMap;
 
 use crate::graphics::{
     BitmapInternal, BitmapKey, PlaneInternal, PlaneKey, SpriteInternal, SpriteKey, TileKey,
-    TilemapInternal, ViewportInternal, ViewportKey, WindowData, WindowKey,
+    TilemapInternal, ViewportInternal, ViewportKey, WindowData, WindowKey, ZList,
 };
 
 #[derive(Default)]
@@ -36,4 +36,22 @@ impl Arenas {
         "window is missing from graphics arena! please report you you encountered this";
     pub(crate) const VIEWPORT_MISSING: &'static str =
         "viewport is missing from graphics arena! please report you you encountered this";
+
+    /// Disposes everything apart from `global_viewport`, which is emptied.
+    ///
+    /// Scripts can still hold on to objects from before, so lookups with their keys have to handle them being gone.
+    pub(crate) fn dispose_all(&mut self, global_viewport: ViewportKey) {
+        self.sprite.clear();
+        self.plane.clear();
+        self.tilemap.clear();
+        self.bitmap.clear();
+        self.window.clear();
+
+        self.viewport.retain(|key, _| key == global_viewport);
+        let global_viewport = self
+            .viewport
+            .get_mut(global_viewport)
+            .expect(Self::VIEWPORT_MISSING);
+        global_viewport.z_list = ZList::new();
+    }
 }
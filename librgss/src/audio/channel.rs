@@ -0,0 +1,94 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use super::{stream::Stream, VolumeCategory};
+
+/// A channel that plays one track at a time. Sound effects are not played on a channel, as they can overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Bgm,
+    Bgs,
+    Me,
+    /// A numbered channel that plays its track once (modshot's `ch_play`).
+    Numbered(u32),
+    /// A numbered channel that loops its track (modshot's `lch_play`).
+    LoopingNumbered(u32),
+}
+
+impl Channel {
+    pub fn is_looping(self) -> bool {
+        matches!(self, Self::Bgm | Self::Bgs | Self::LoopingNumbered(_))
+    }
+
+    /// The volume slider that controls this channel.
+    pub fn volume_category(self) -> VolumeCategory {
+        match self {
+            Self::Bgm => VolumeCategory::Bgm,
+            Self::Bgs | Self::LoopingNumbered(_) => VolumeCategory::Bgs,
+            Self::Me => VolumeCategory::Me,
+            Self::Numbered(_) => VolumeCategory::Se,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct ChannelState {
+    pub current: Option<Stream>,
+    /// Tracks that were replaced by a crossfade, and are still fading out.
+    pub fading_out: Vec<Stream>,
+}
+
+impl ChannelState {
+    /// Applies a new gain from the volume sliders to every track on this channel.
+    pub fn set_mix(&mut self, mix: f32) {
+        for stream in self.current.iter_mut().chain(self.fading_out.iter_mut()) {
+            stream.set_mix(mix);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.fading_out.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current.is_some() || !self.fading_out.is_empty()
+    }
+
+    pub fn needs_tick(&self) -> bool {
+        !self.fading_out.is_empty() || self.current.as_ref().is_some_and(Stream::is_fading)
+    }
+
+    /// Updates fades, dropping any track that has finished fading out.
+    ///
+    /// Returns true if the current track finished (either by fading out, or by reaching the end).
+    pub fn tick(&mut self, now: Duration) -> bool {
+        self.fading_out
+            .retain_mut(|stream| !stream.update_fade(now) && !stream.sink.empty());
+
+        let finished = self
+            .current
+            .as_mut()
+            .is_some_and(|stream| stream.update_fade(now) || stream.sink.empty());
+        if finished {
+            self.current = None;
+        }
+        finished
+    }
+}
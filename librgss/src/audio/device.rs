@@ -0,0 +1,314 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, WrapErr};
+use parking_lot::Mutex;
+use rodio::{
+    cpal::{
+        self,
+        traits::{HostTrait, StreamTrait},
+        FromSample, SampleFormat, SizedSample,
+    },
+    source::UniformSourceIterator,
+    DeviceTrait, Source,
+};
+
+use super::output::{Mixer, MIXER_CHANNELS, MIXER_SAMPLE_RATE};
+
+/// How many samples are mixed at a time, so the mixer isn't locked for every sample.
+const BLOCK_SAMPLES: usize = 512;
+
+/// The names of the output devices on this system.
+pub fn output_devices() -> Vec<String> {
+    let devices = match cpal::default_host().output_devices() {
+        Ok(devices) => devices,
+        Err(error) => {
+            eprintln!("Failed to list audio output devices: {error}");
+            return Vec::new();
+        }
+    };
+    devices.filter_map(|device| device.name().ok()).collect()
+}
+
+/// The name of the device [`DeviceStream::open`] would use.
+///
+/// This is the device named `wanted` if it exists, otherwise the default device.
+pub(super) fn preferred_device_name(wanted: Option<&str>) -> Option<String> {
+    find_device(wanted).and_then(|device| device.name().ok())
+}
+
+/// Keeps track of which device should be used on its own thread, as finding devices can take a while
+/// (and the audio thread has better things to do).
+///
+/// Listing devices is slow (and on ALSA, opens every device and prints errors for the ones that can't be), so it's
+/// only done to find the device named in the config after the stream fails. Otherwise only the name of the default
+/// device is checked, and only if no device was named.
+pub(super) struct DeviceWatcher {
+    preferred: Arc<Mutex<Option<String>>>,
+    /// Set while there's no stream, so the watcher should look for a device to reopen.
+    searching: Arc<AtomicBool>,
+}
+
+impl DeviceWatcher {
+    /// Checks for the device named `wanted` (or the default device) every `interval`.
+    pub fn spawn(wanted: Option<String>, interval: Duration) -> Self {
+        Self::spawn_with(interval, move |searching| {
+            // a healthy stream on the named device (or on the default if it's missing) is kept until it fails
+            if wanted.is_some() && !searching {
+                return None;
+            }
+            preferred_device_name(wanted.as_deref())
+        })
+    }
+
+    /// Calls `check` every `interval` to find the preferred device, passing whether the stream is gone.
+    /// Stops when the watcher is dropped.
+    pub fn spawn_with(
+        interval: Duration,
+        mut check: impl FnMut(bool) -> Option<String> + Send + 'static,
+    ) -> Self {
+        let preferred = Arc::new(Mutex::new(None));
+        let searching = Arc::new(AtomicBool::new(false));
+        let weak: Weak<Mutex<Option<String>>> = Arc::downgrade(&preferred);
+        let thread_searching = searching.clone();
+        std::thread::Builder::new()
+            .name("audio device watcher".to_string())
+            .spawn(move || loop {
+                let name = check(thread_searching.load(Ordering::Relaxed));
+                let Some(preferred) = weak.upgrade() else {
+                    break;
+                };
+                *preferred.lock() = name;
+                drop(preferred);
+                std::thread::sleep(interval);
+            })
+            .expect("failed to spawn audio device watcher thread");
+        Self {
+            preferred,
+            searching,
+        }
+    }
+
+    /// Tells the watcher whether the stream is gone, and so whether it should look for another device.
+    pub fn set_searching(&self, searching: bool) {
+        if self.searching.swap(searching, Ordering::Relaxed) != searching {
+            // whatever was found is for the old state
+            *self.preferred.lock() = None;
+        }
+    }
+
+    /// The device that should be used, as of the last check. `None` if there is none, or it hasn't been checked yet.
+    pub fn preferred(&self) -> Option<String> {
+        self.preferred.lock().clone()
+    }
+}
+
+fn find_device(wanted: Option<&str>) -> Option<cpal::Device> {
+    let host = cpal::default_host();
+    let named = wanted.and_then(|wanted| {
+        host.output_devices()
+            .ok()?
+            .find(|device| device.name().is_ok_and(|name| name == wanted))
+    });
+    named.or_else(|| host.default_output_device())
+}
+
+/// A stream playing the audio thread's mixer on an output device.
+///
+/// Sinks are added to the mixer rather than the stream, so they keep playing if the stream is rebuilt.
+pub(super) struct DeviceStream {
+    // not Send, which is why the output has to be created on the audio thread
+    _stream: cpal::Stream,
+    pub name: String,
+    /// Set from the device's thread when the stream errors (usually because the device was unplugged).
+    failed: Arc<AtomicBool>,
+}
+
+impl DeviceStream {
+    /// Opens the device named `wanted`, or the default device if it's `None` or can't be found.
+    pub fn open(wanted: Option<&str>, mixer: &Arc<Mutex<Mixer>>) -> color_eyre::Result<Self> {
+        let device = find_device(wanted).ok_or_else(|| eyre!("no audio output device found"))?;
+        let name = device.name()?;
+        if let Some(wanted) = wanted.filter(|&wanted| wanted != name) {
+            eprintln!("Audio device {wanted:?} not found, using {name:?} instead");
+            eprintln!("Available devices: {:?}", output_devices());
+        }
+
+        let device_config = device.default_output_config()?;
+        println!("Using audio device {name}");
+        println!("Device config: {device_config:#?}");
+
+        let failed = Arc::new(AtomicBool::new(false));
+        let config = device_config.config();
+        let stream = match device_config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer, &failed),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer, &failed),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer, &failed),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, mixer, &failed),
+            format => Err(eyre!("unsupported sample format {format}")),
+        }
+        .wrap_err_with(|| format!("failed to open audio device {name}"))?;
+        stream.play()?;
+
+        Ok(Self {
+            _stream: stream,
+            name,
+            failed,
+        })
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: &Arc<Mutex<Mixer>>,
+    failed: &Arc<AtomicBool>,
+) -> color_eyre::Result<cpal::Stream> {
+    let source = MixerSource {
+        mixer: mixer.clone(),
+        block: Vec::with_capacity(BLOCK_SAMPLES),
+        index: 0,
+    };
+    let mut samples: UniformSourceIterator<_, f32> =
+        UniformSourceIterator::new(source, config.channels, config.sample_rate.0);
+
+    let failed = failed.clone();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for sample in data {
+                *sample = T::from_sample(samples.next().unwrap_or(0.0));
+            }
+        },
+        move |error| {
+            eprintln!("Audio output stream error: {error}");
+            failed.store(true, Ordering::Relaxed);
+        },
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Pulls from the shared mixer a block at a time.
+struct MixerSource {
+    mixer: Arc<Mutex<Mixer>>,
+    block: Vec<f32>,
+    index: usize,
+}
+
+impl Iterator for MixerSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.block.len() {
+            self.block.resize(BLOCK_SAMPLES, 0.0);
+            // this runs on the device's realtime thread, so don't wait on the audio thread
+            match self.mixer.try_lock() {
+                Some(mut mixer) => mixer.fill(&mut self.block),
+                None => self.block.fill(0.0),
+            }
+            self.index = 0;
+        }
+
+        let sample = self.block[self.index];
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MixerSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        MIXER_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MIXER_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn mixer_source_is_silent_while_locked() {
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let sink = mixer.lock().new_sink();
+        sink.append(rodio::source::SineWave::new(440.0));
+
+        let mut source = MixerSource {
+            mixer: mixer.clone(),
+            block: Vec::new(),
+            index: 0,
+        };
+        let guard = mixer.lock();
+        assert!((&mut source)
+            .take(BLOCK_SAMPLES)
+            .all(|sample| sample == 0.0));
+        drop(guard);
+        // the sink's queue can start with a block of silence of its own
+        assert!((&mut source)
+            .take(BLOCK_SAMPLES * 4)
+            .any(|sample| sample != 0.0));
+    }
+
+    fn wait_for_preferred(watcher: &DeviceWatcher, name: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.preferred().as_deref() != Some(name) {
+            assert!(
+                Instant::now() < deadline,
+                "watcher never found {name} (found {:?})",
+                watcher.preferred()
+            );
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn watcher_reports_the_preferred_device() {
+        let watcher = DeviceWatcher::spawn_with(Duration::from_millis(1), |searching| {
+            Some(if searching { "headphones" } else { "speakers" }.to_string())
+        });
+        wait_for_preferred(&watcher, "speakers");
+
+        // the stream failed, so the watcher looks harder
+        watcher.set_searching(true);
+        wait_for_preferred(&watcher, "headphones");
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use camino::Utf8PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Unable to find {0}")]
+    NotFound(Utf8PathBuf),
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: Utf8PathBuf,
+        source: crate::FileSystemError,
+    },
+    #[error("Failed to decode {path}: {source}")]
+    Decode {
+        path: Utf8PathBuf,
+        source: rodio::decoder::DecoderError,
+    },
+    #[error("Failed to read MIDI file {path}: {source}")]
+    Midi {
+        path: Utf8PathBuf,
+        source: rustysynth::MidiFileError,
+    },
+    #[error("Failed to load SoundFont {path}: {source}")]
+    SoundFont {
+        path: Utf8PathBuf,
+        source: rustysynth::SoundFontError,
+    },
+    #[error("The audio thread has stopped")]
+    Disconnected,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Reads a whole file, distinguishing between missing files and other errors.
+pub(super) fn read_file(
+    filesystem: &crate::FileSystem,
+    path: &camino::Utf8Path,
+) -> Result<Vec<u8>> {
+    read_start(filesystem, path, u64::MAX)
+}
+
+/// Reads (at most) the first `len` bytes of a file.
+pub(super) fn read_start(
+    filesystem: &crate::FileSystem,
+    path: &camino::Utf8Path,
+    len: u64,
+) -> Result<Vec<u8>> {
+    let read = || {
+        let mut data = vec![];
+        let file = filesystem.read_file(path)?;
+        std::io::Read::read_to_end(&mut std::io::Read::take(file, len), &mut data)?;
+        Ok(data)
+    };
+    read().map_err(|source| match source {
+        crate::FileSystemError::NotExist => Error::NotFound(path.to_path_buf()),
+        source => Error::Read {
+            path: path.to_path_buf(),
+            source,
+        },
+    })
+}
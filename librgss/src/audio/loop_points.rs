@@ -0,0 +1,190 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Cursor;
+
+/// Where a looping track loops, in frames.
+///
+/// RPG Maker reads these from the `LOOPSTART`/`LOOPLENGTH` comments of ogg vorbis files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct LoopPoints {
+    pub start: u64,
+    /// `None` loops at the end of the track.
+    pub length: Option<u64>,
+}
+
+const IDENTIFICATION_HEADER: &[u8] = b"\x01vorbis";
+const COMMENT_HEADER: &[u8] = b"\x03vorbis";
+
+impl LoopPoints {
+    pub const WHOLE_TRACK: Self = Self {
+        start: 0,
+        length: None,
+    };
+
+    /// Reads the loop points of an ogg vorbis file.
+    ///
+    /// Returns `None` if `data` is not an ogg vorbis file or has no `LOOPSTART` comment.
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let comments = read_comments(data)?;
+        let find = |key: &str| {
+            comments
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .and_then(|(_, v)| v.trim().parse::<u64>().ok())
+        };
+
+        let start = find("LOOPSTART")?;
+        // rpg maker treats a length of 0 the same as no length at all
+        let length = find("LOOPLENGTH").filter(|&length| length > 0);
+        Some(Self { start, length })
+    }
+
+    /// The frame the track jumps back to `start` at, if not the end of the track.
+    pub fn end(&self) -> Option<u64> {
+        self.length.map(|length| self.start + length)
+    }
+}
+
+/// Reads the comments of an ogg vorbis file as `(key, value)` pairs.
+fn read_comments(data: &[u8]) -> Option<Vec<(String, String)>> {
+    let mut reader = ogg::PacketReader::new(Cursor::new(data));
+
+    let identification = reader.read_packet().ok()??;
+    if !identification.data.starts_with(IDENTIFICATION_HEADER) {
+        return None;
+    }
+    let comment = reader.read_packet().ok()??;
+    let mut rest = comment.data.strip_prefix(COMMENT_HEADER)?;
+
+    let _vendor = read_string(&mut rest)?;
+    let count = read_u32(&mut rest)?;
+    let comments = (0..count)
+        .map_while(|_| read_string(&mut rest))
+        .filter_map(|comment| {
+            let (key, value) = comment.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    Some(comments)
+}
+
+fn read_u32(rest: &mut &[u8]) -> Option<usize> {
+    let (bytes, tail) = rest.split_first_chunk::<4>()?;
+    *rest = tail;
+    Some(u32::from_le_bytes(*bytes) as usize)
+}
+
+fn read_string(rest: &mut &[u8]) -> Option<String> {
+    let len = read_u32(rest)?;
+    let bytes = rest.get(..len)?;
+    *rest = &rest[len..];
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the headers of an ogg vorbis file with the given comments. There is no audio data, which is all we need to read comments.
+    fn ogg_fixture(comments: &[&str]) -> Vec<u8> {
+        let mut identification = IDENTIFICATION_HEADER.to_vec();
+        identification.extend(0u32.to_le_bytes()); // version
+        identification.push(2); // channels
+        identification.extend(44100u32.to_le_bytes());
+        identification.extend([0; 12]); // bitrates
+        identification.push(0xB8); // block sizes
+        identification.push(1); // framing
+
+        let mut comment = COMMENT_HEADER.to_vec();
+        let vendor = b"sapphire test";
+        comment.extend((vendor.len() as u32).to_le_bytes());
+        comment.extend(vendor);
+        comment.extend((comments.len() as u32).to_le_bytes());
+        for c in comments {
+            comment.extend((c.len() as u32).to_le_bytes());
+            comment.extend(c.as_bytes());
+        }
+        comment.push(1); // framing
+
+        let mut writer = ogg::PacketWriter::new(Vec::new());
+        writer
+            .write_packet(
+                identification.into_boxed_slice(),
+                1,
+                ogg::PacketWriteEndInfo::EndPage,
+                0,
+            )
+            .unwrap();
+        writer
+            .write_packet(
+                comment.into_boxed_slice(),
+                1,
+                ogg::PacketWriteEndInfo::EndStream,
+                0,
+            )
+            .unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn reads_loop_points() {
+        let data = ogg_fixture(&["TITLE=Field", "LOOPSTART=44100", "LOOPLENGTH=88200"]);
+        assert_eq!(
+            LoopPoints::read(&data),
+            Some(LoopPoints {
+                start: 44100,
+                length: Some(88200)
+            })
+        );
+    }
+
+    #[test]
+    fn keys_are_case_insensitive() {
+        let data = ogg_fixture(&["loopstart=10", "LoopLength=20"]);
+        let loop_points = LoopPoints::read(&data).unwrap();
+        assert_eq!(loop_points.end(), Some(30));
+    }
+
+    #[test]
+    fn missing_length_loops_at_end() {
+        let data = ogg_fixture(&["LOOPSTART=1000"]);
+        assert_eq!(
+            LoopPoints::read(&data),
+            Some(LoopPoints {
+                start: 1000,
+                length: None
+            })
+        );
+
+        let data = ogg_fixture(&["LOOPSTART=1000", "LOOPLENGTH=0"]);
+        assert_eq!(LoopPoints::read(&data).unwrap().end(), None);
+    }
+
+    #[test]
+    fn missing_start_is_none() {
+        assert_eq!(LoopPoints::read(&ogg_fixture(&[])), None);
+        assert_eq!(LoopPoints::read(&ogg_fixture(&["LOOPLENGTH=20"])), None);
+        assert_eq!(LoopPoints::read(&ogg_fixture(&["LOOPSTART=abc"])), None);
+    }
+
+    #[test]
+    fn not_ogg_is_none() {
+        assert_eq!(LoopPoints::read(b"RIFF\0\0\0\0WAVEfmt "), None);
+        assert_eq!(LoopPoints::read(&[]), None);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{sync::Arc, time::Duration};
+
+use rodio::Source;
+use rustysynth::{
+    MidiFile, MidiFileSequencer, SoundFont, Synthesizer, SynthesizerError, SynthesizerSettings,
+};
+
+use super::source::{Position, Skip};
+
+const SAMPLE_RATE: u32 = 44100;
+/// How many frames are rendered at once.
+const BLOCK_FRAMES: usize = 512;
+
+/// Used when no SoundFont is configured.
+pub(super) const DEFAULT_SOUNDFONT_PATH: &str = "soundfont.sf2";
+
+/// Synthesizes a MIDI file with a SoundFont.
+///
+/// Looping tracks loop back to the RPG Maker loop marker (CC #111), or the start of the track if there isn't one.
+pub(super) struct MidiSource {
+    sequencer: MidiFileSequencer,
+    looping: bool,
+    left: Vec<f32>,
+    right: Vec<f32>,
+    /// Samples read from the current block.
+    index: usize,
+    position: Position,
+}
+
+impl MidiSource {
+    pub fn new(
+        soundfont: &Arc<SoundFont>,
+        midi_file: &Arc<MidiFile>,
+        looping: bool,
+    ) -> Result<(Self, Position), SynthesizerError> {
+        let settings = SynthesizerSettings::new(SAMPLE_RATE as i32);
+        let synthesizer = Synthesizer::new(soundfont, &settings)?;
+        let mut sequencer = MidiFileSequencer::new(synthesizer);
+        sequencer.play(midi_file, looping);
+
+        let position = Position::new(SAMPLE_RATE);
+        let this = Self {
+            sequencer,
+            looping,
+            left: vec![0.0; BLOCK_FRAMES],
+            right: vec![0.0; BLOCK_FRAMES],
+            index: BLOCK_FRAMES * 2,
+            position: position.clone(),
+        };
+
+        Ok((this, position))
+    }
+
+    /// Returns false if the track has ended.
+    fn render_block(&mut self) -> bool {
+        if !self.looping && self.sequencer.end_of_sequence() {
+            return false;
+        }
+
+        self.sequencer.render(&mut self.left, &mut self.right);
+        self.index = 0;
+        self.position
+            .set((self.sequencer.get_position() * SAMPLE_RATE as f64) as u64);
+        true
+    }
+}
+
+impl Skip for MidiSource {
+    // the sequencer can't seek, so render (and throw away) everything up to the start
+    fn skip_to(&mut self, start: Duration) {
+        let skipped_blocks = (start.as_secs_f64() * SAMPLE_RATE as f64) as usize / BLOCK_FRAMES;
+        for _ in 0..skipped_blocks {
+            if !self.render_block() {
+                break;
+            }
+        }
+    }
+}
+
+impl Iterator for MidiSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= BLOCK_FRAMES * 2 && !self.render_block() {
+            return None;
+        }
+
+        let frame = self.index / 2;
+        let sample = if self.index.is_multiple_of(2) {
+            self.left[frame]
+        } else {
+            self.right[frame]
+        };
+        self.index += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MidiSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustysynth::MidiFileLoopType;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        chunk
+    }
+
+    fn list(kind: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        chunks.iter().for_each(|c| data.extend(c));
+        chunk(b"LIST", &data)
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(20, 0);
+        bytes
+    }
+
+    /// A SoundFont with a single preset that plays a looping square wave.
+    fn square_soundfont() -> Arc<SoundFont> {
+        let wave = (0..64)
+            .flat_map(|i: i16| if i % 16 < 8 { 16000_i16 } else { -16000 }.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let preset = |label: &str, bag: u16| {
+            let mut record = name(label);
+            record.extend([0, 0, 0, 0]); // patch and bank
+            record.extend(bag.to_le_bytes());
+            record.extend([0; 12]); // library, genre, morphology
+            record
+        };
+        let instrument = |label: &str, bag: u16| [name(label), bag.to_le_bytes().to_vec()].concat();
+        let bags = |bags: &[u16]| {
+            bags.iter()
+                .flat_map(|&b| [b, 0])
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        };
+        let generators = |generators: &[(u16, u16)]| {
+            generators
+                .iter()
+                .flat_map(|&(kind, amount)| [kind, amount])
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        };
+        let sample = |label: &str, end: i32, loop_start: i32, loop_end: i32| {
+            let mut record = name(label);
+            for value in [0, end, loop_start, loop_end, 44100] {
+                record.extend(value.to_le_bytes());
+            }
+            record.extend([60, 0, 0, 0, 1, 0]); // pitch, correction, link, mono
+            record
+        };
+
+        let pdta = list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &[preset("square", 0), preset("EOP", 1)].concat()),
+                chunk(b"pbag", &bags(&[0, 1])),
+                // instrument 0
+                chunk(b"pgen", &generators(&[(41, 0), (0, 0)])),
+                chunk(
+                    b"inst",
+                    &[instrument("square", 0), instrument("EOI", 1)].concat(),
+                ),
+                chunk(b"ibag", &bags(&[0, 2])),
+                // loop continuously, sample 0
+                chunk(b"igen", &generators(&[(54, 1), (53, 0), (0, 0)])),
+                chunk(
+                    b"shdr",
+                    &[sample("square", 48, 8, 40), sample("EOS", 0, 0, 0)].concat(),
+                ),
+            ],
+        );
+        let info = list(b"INFO", &[chunk(b"ifil", &[2, 0, 1, 0])]);
+        let sdta = list(b"sdta", &[chunk(b"smpl", &wave)]);
+
+        let riff = chunk(b"RIFF", &[b"sfbk".to_vec(), info, sdta, pdta].concat());
+        Arc::new(SoundFont::new(&mut riff.as_slice()).unwrap())
+    }
+
+    /// At 120bpm, plays a note for 0.25s, sets the loop marker at 0.5s, and ends at 1s.
+    fn marked_midi() -> Arc<MidiFile> {
+        let track: &[u8] = &[
+            0x00, 0x90, 60, 100, // note on
+            0x81, 0x70, 0x80, 60, 0, // note off at tick 240
+            0x81, 0x70, 0xB0, 111, 0, // loop marker at tick 480
+            0x83, 0x60, 0xFF, 0x2F, 0, // end of track at tick 960
+        ];
+        let mut smf = b"MThd\0\0\0\x06\0\0\0\x01\x01\xE0MTrk".to_vec();
+        smf.extend((track.len() as u32).to_be_bytes());
+        smf.extend(track);
+
+        let file = MidiFile::new_with_loop_type(&mut smf.as_slice(), MidiFileLoopType::RpgMaker);
+        Arc::new(file.unwrap())
+    }
+
+    /// The loudness of the left channel between `from` and `to` seconds.
+    fn rms(samples: &[f32], from: f64, to: f64) -> f32 {
+        let frames = (from * SAMPLE_RATE as f64) as usize..(to * SAMPLE_RATE as f64) as usize;
+        let sum = frames.clone().map(|i| samples[i * 2].powi(2)).sum::<f32>();
+        (sum / frames.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn loops_back_to_the_loop_marker() {
+        let (source, position) =
+            MidiSource::new(&square_soundfont(), &marked_midi(), true).unwrap();
+        let samples = source
+            .take((1.25 * SAMPLE_RATE as f64) as usize * 2)
+            .collect::<Vec<_>>();
+
+        let note = rms(&samples, 0.0, 0.25);
+        assert!(note > 0.01, "the note should be heard ({note})");
+        // looping to the start would play the note again
+        let after_loop = rms(&samples, 1.05, 1.25);
+        assert!(
+            after_loop < note / 10.,
+            "looped to the start ({after_loop} vs {note})"
+        );
+
+        let position = position.get().as_secs_f64();
+        assert!(
+            (position - 0.75).abs() < 0.03,
+            "position after looping was {position}"
+        );
+    }
+
+    #[test]
+    fn ends_without_looping() {
+        let (source, _) = MidiSource::new(&square_soundfont(), &marked_midi(), false).unwrap();
+        let frames = source.count() / 2;
+        assert!(frames < 2 * SAMPLE_RATE as usize, "played {frames} frames");
+    }
+}
@@ -16,243 +16,1151 @@
 // along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
+    collections::HashMap,
     sync::{
         mpsc::{Receiver, RecvTimeoutError, Sender},
         Arc,
     },
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use camino::Utf8PathBuf;
-use rodio::{cpal::traits::HostTrait, DeviceTrait};
+use camino::{Utf8Path, Utf8PathBuf};
+use parking_lot::Mutex;
+use rustysynth::SoundFont;
 
-use crate::FileSystem;
+use crate::{AudioBackend, AudioConfig, FileSystem};
+
+mod channel;
+pub use channel::Channel;
+use channel::ChannelState;
+
+mod device;
+pub use device::output_devices;
+
+mod error;
+pub use error::{Error, Result};
+
+mod loop_points;
+
+mod midi;
+use midi::MidiSource;
+
+mod se_cache;
+use se_cache::SeCache;
+
+mod se_voices;
+use se_voices::SeVoices;
+
+mod source;
+use source::{Deferred, Skip, Track as _, TrackSource};
+
+mod output;
+use output::Output;
+
+mod track;
+use track::{Track, TrackKind, TrackRequest};
+
+mod stream;
+use stream::Stream;
+
+mod volume;
+use volume::VolumeSaver;
+pub use volume::{VolumeCategory, Volumes};
+
+/// How often the audio thread wakes up while an effect (like a fade) is running.
+const EFFECT_TICK: Duration = Duration::from_millis(16);
+/// How long a track fades in for when it is started partway through (like RGSS3 does when resuming the BGM).
+const OFFSET_FADE_IN: Duration = Duration::from_millis(1000);
+/// Where the offline backend writes to if no path is configured.
+const DEFAULT_MIXDOWN_PATH: &str = "mixdown.wav";
+const DEFAULT_SE_CACHE_SIZE: usize = 32 * 1024 * 1024;
+const DEFAULT_SE_VOICES: usize = 16;
+const DEFAULT_VOLUME_PATH: &str = "volume.toml";
 
 pub struct Audio {
     sender: Sender<Event>,
+    filesystem: Arc<FileSystem>,
+    soundfont_path: Utf8PathBuf,
+    /// Loaded the first time a MIDI file is played (or when the game calls `Audio.setup_midi`).
+    soundfont: Mutex<Option<Arc<SoundFont>>>,
+    se_cache: Mutex<SeCache>,
+    volumes: Mutex<Volumes>,
+    /// Whether time only passes when [`Audio::advance`] is called.
+    offline: bool,
+    /// Errors from the audio thread about tracks that passed [`Audio::check`], but couldn't be played.
+    errors: crossbeam::channel::Receiver<Error>,
 }
 
 #[derive(Debug)]
 enum Event {
-    PlayBGM(PlayArgs),
-    StopBGM,
-    FadeBGM(u32),
-    PlayBGS(PlayArgs),
-    StopBGS,
-    FadeBGS(u32),
-    PlayME(PlayArgs),
-    StopME,
-    FadeME(u32),
-    PlaySE(PlayArgs),
+    Play(Channel, PlayArgs<TrackRequest>),
+    Stop(Channel),
+    Fade(Channel, Duration),
+    Crossfade(Channel, PlayArgs<TrackRequest>, Duration),
+    Status(Channel, Sender<Option<PlaybackStatus>>),
+    Advance(Duration),
+    SetVolumes(Volumes),
+    PlaySE(PlayArgs<Track>),
     StopSE,
+    StopAll,
     Exit,
 }
 
 #[derive(Debug)]
-struct PlayArgs {
-    path: Utf8PathBuf,
+struct PlayArgs<T> {
+    track: T,
     pitch: u32,
     volume: u32,
+    start: Duration,
+    /// Fade in when starting partway through the track.
+    fade_in_on_offset: bool,
 }
 
-struct AudioState {
-    output_stream: rodio::OutputStream,
-    output_stream_handle: rodio::OutputStreamHandle,
+/// What a channel is currently playing.
+#[derive(Debug, Clone)]
+pub struct PlaybackStatus {
+    pub path: Utf8PathBuf,
+    /// How far into the track playback is. Looping tracks jump back to their loop start.
+    pub position: Duration,
+    pub volume: u32,
+    pub pitch: u32,
+}
 
+struct AudioState {
+    output: Output,
+    /// Tracks are read here rather than on the caller's thread, as reading a whole BGM takes a while.
     filesystem: Arc<FileSystem>,
+    /// Where errors the game should hear about are sent, as they can only be raised from the caller's thread.
+    errors: crossbeam::channel::Sender<Error>,
 
-    bgm: Option<Stream>,
-    se_sinks: Vec<rodio::Sink>,
-}
-
-struct Stream {
-    sink: rodio::Sink,
-    path: Utf8PathBuf,
+    channels: HashMap<Channel, ChannelState>,
+    se_voices: SeVoices,
+    volumes: Volumes,
 }
 
-// TODO better error handling in this function
 fn audio_thread_fun(
     receiver: Receiver<Event>,
+    errors: crossbeam::channel::Sender<Error>,
     filesystem: Arc<FileSystem>,
+    config: AudioConfig,
+    volumes: Volumes,
+    volume_path: Utf8PathBuf,
 ) -> color_eyre::Result<()> {
-    // FIXME apparently we can leak output_stream (which is not Send+Sync)
-    let device = rodio::cpal::default_host().default_output_device().unwrap();
-    let device_name = device.name().unwrap();
-    let device_config = device.default_output_config().unwrap();
-    // .supported_output_configs()
-    // .unwrap()
-    // .max_by(|c1, c2| c1.channels().cmp(&c2.channels()))
-    // .unwrap()
-    // .with_max_sample_rate();
-
-    println!("Using platform default audio device ({device_name})",);
-    println!("Device config: {device_config:#?}",);
-
-    let (output_stream, output_stream_handle) =
-        rodio::OutputStream::try_from_device_config(&device, device_config)?;
-    let mut state = AudioState {
-        output_stream,
-        output_stream_handle,
-        filesystem,
-        bgm: None,
-        se_sinks: Vec::with_capacity(16),
-    };
+    let mixdown_path = config
+        .mixdown_path
+        .as_deref()
+        .unwrap_or(Utf8Path::new(DEFAULT_MIXDOWN_PATH));
+    let output = Output::new(config.backend, config.device.as_deref(), mixdown_path)?;
+    let mut state = AudioState::new(output, filesystem, errors, &config, volumes);
+    let mut volume_saver = VolumeSaver::new(volume_path);
 
     // TODO extract while loop body into a function to process events
-    // TODO gradual event timeout (have infinite timeout when audio processing effects are not required)
     loop {
-        let result = receiver.recv_timeout(std::time::Duration::from_millis(16));
-        let event = match result {
+        // only wake up periodically if there's something to update, otherwise block until the next event
+        let timeout = [
+            state.needs_tick().then_some(EFFECT_TICK),
+            state.output.poll_interval(),
+            volume_saver.due_in(Instant::now()),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let result = match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match result {
             Ok(Event::Exit) | Err(RecvTimeoutError::Disconnected) => break,
-            Err(RecvTimeoutError::Timeout) => {
-                state.handle_timeout();
-                continue;
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(event) => {
+                if !matches!(event, Event::Advance(_)) {
+                    println!("{:?}", event);
+                }
+                if let Event::SetVolumes(volumes) = event {
+                    volume_saver.changed(volumes);
+                }
+                // catch up first, so the event happens at the right point in playback
+                state.output.update();
+                state.process(event);
             }
-            Ok(o) => o,
-        };
+        }
 
-        println!("{:?}", event);
-        state.process(event);
+        state.output.update();
+        state.tick();
+        volume_saver.update(Instant::now());
     }
 
-    Ok(())
+    volume_saver.flush(Instant::now());
+    state.output.finish()
 }
 
 impl AudioState {
+    fn new(
+        output: Output,
+        filesystem: Arc<FileSystem>,
+        errors: crossbeam::channel::Sender<Error>,
+        config: &AudioConfig,
+        volumes: Volumes,
+    ) -> Self {
+        Self {
+            output,
+            filesystem,
+            errors,
+            channels: HashMap::new(),
+            se_voices: SeVoices::new(
+                config.se_voices.unwrap_or(DEFAULT_SE_VOICES),
+                config.se_eviction,
+                volumes.gain(VolumeCategory::Se),
+            ),
+            volumes,
+        }
+    }
+
     fn process(&mut self, event: Event) {
         match event {
-            Event::PlayBGM(args) => match &mut self.bgm {
-                Some(stream) if stream.path == args.path => {
-                    stream.sink.set_volume(args.volume as f32 / 100. * 0.80);
-                    stream.sink.set_speed(args.pitch as f32 / 100.);
+            Event::Play(channel, args) => {
+                let state = self.channels.entry(channel).or_default();
+                // replaying the same looping track only updates its volume and pitch
+                if let Some(stream) = state.current.as_mut().filter(|stream| {
+                    channel.is_looping() && stream.path == args.track.path && args.start.is_zero()
+                }) {
+                    stream.set_volume_pitch(args.volume, args.pitch);
+                } else {
+                    match self.new_stream(channel, args) {
+                        Ok(stream) => {
+                            self.channels.entry(channel).or_default().current = Some(stream)
+                        }
+                        Err(error) => self.report(error),
+                    }
                 }
-                _ => {
-                    let sink = rodio::Sink::try_new(&self.output_stream_handle).unwrap();
-
-                    let file = self.filesystem.read_file(&args.path).unwrap();
-                    let decoder = rodio::Decoder::new_looped(file).unwrap();
-                    sink.append(decoder);
-                    sink.set_volume(args.volume as f32 / 100.);
-                    sink.set_speed(args.pitch as f32 / 100.);
-
-                    self.bgm = Some(Stream {
-                        sink,
-                        path: args.path,
-                    })
+
+                self.after_play(channel);
+            }
+            Event::Stop(channel) => {
+                if let Some(state) = self.channels.get_mut(&channel) {
+                    state.stop();
+                }
+                if channel == Channel::Me {
+                    self.resume_bgm();
                 }
-            },
-            Event::StopBGM => {
-                self.bgm = None;
             }
-            Event::FadeBGM(_) => {}
-            Event::PlayBGS(_) => {}
-            Event::StopBGS => {}
-            Event::FadeBGS(_) => {}
-            Event::PlayME(_) => {}
-            Event::StopME => {}
-            Event::FadeME(_) => {}
-            Event::PlaySE(args) => {
-                let sink = rodio::Sink::try_new(&self.output_stream_handle).unwrap();
+            Event::Fade(channel, duration) => {
+                if let Some(stream) = self
+                    .channels
+                    .get_mut(&channel)
+                    .and_then(|state| state.current.as_mut())
+                {
+                    stream.fade_out(duration, self.output.now());
+                }
+            }
+            Event::Crossfade(channel, args, duration) => {
+                let new = self.new_stream(channel, args);
 
-                let file = self.filesystem.read_file(args.path).unwrap();
-                let decoder = rodio::Decoder::new(file).unwrap();
-                sink.append(decoder);
-                sink.set_volume(args.volume as f32 / 100. * 0.8);
-                sink.set_speed(args.pitch as f32 / 100.);
+                let state = self.channels.entry(channel).or_default();
+                if let Some(mut old) = state.current.take() {
+                    old.fade_out(duration, self.output.now());
+                    state.fading_out.push(old);
+                }
+                match new {
+                    Ok(mut stream) => {
+                        stream.fade_in(duration, self.output.now());
+                        state.current = Some(stream);
+                    }
+                    Err(error) => self.report(error),
+                }
 
-                self.se_sinks.push(sink)
+                self.after_play(channel);
+            }
+            Event::Status(channel, reply) => {
+                let status = self
+                    .channels
+                    .get(&channel)
+                    .and_then(|state| state.current.as_ref())
+                    .map(Stream::status);
+                let _ = reply.send(status);
+            }
+            Event::Advance(duration) => {
+                if let Err(error) = self.output.advance(duration) {
+                    eprintln!("{error:?}");
+                }
+            }
+            Event::SetVolumes(volumes) => {
+                self.volumes = volumes;
+                for (channel, state) in self.channels.iter_mut() {
+                    state.set_mix(volumes.gain(channel.volume_category()));
+                }
+                self.se_voices.set_mix(volumes.gain(VolumeCategory::Se));
+            }
+            Event::PlaySE(args) => {
+                if !self.se_voices.make_room(args.volume) {
+                    return;
+                }
+                match self.play_track(&args.track, false, Duration::ZERO) {
+                    Ok((sink, _)) => {
+                        sink.set_speed(args.pitch as f32 / 100.);
+                        self.se_voices.push(sink, args.volume)
+                    }
+                    Err(error) => self.report(error),
+                }
+            }
+            Event::StopSE => self.se_voices.stop_all(),
+            Event::StopAll => {
+                self.channels.clear();
+                self.se_voices.stop_all();
             }
-            Event::StopSE => {}
             Event::Exit => {}
         }
     }
 
-    fn handle_timeout(&mut self) {
-        self.se_sinks.retain(|s| !s.empty());
+    /// Sends errors about the track to the game, and prints anything else (which the game can't do anything about).
+    fn report(&self, error: color_eyre::Report) {
+        match error.downcast::<Error>() {
+            Ok(error) => {
+                eprintln!("{error}");
+                let _ = self.errors.send(error);
+            }
+            Err(error) => eprintln!("{error:?}"),
+        }
+    }
+
+    fn new_stream(
+        &mut self,
+        channel: Channel,
+        args: PlayArgs<TrackRequest>,
+    ) -> color_eyre::Result<Stream> {
+        let track = args.track.load(&self.filesystem)?;
+        let (sink, position) = self.play_track(&track, channel.is_looping(), args.start)?;
+
+        let mix = self.volumes.gain(channel.volume_category());
+        let mut stream = Stream::new(sink, track.path, position, args.volume, args.pitch, mix);
+        if args.fade_in_on_offset && !args.start.is_zero() {
+            stream.fade_in(OFFSET_FADE_IN, self.output.now());
+        }
+        Ok(stream)
+    }
+
+    /// Starts playing `track` on a new sink.
+    fn play_track(
+        &self,
+        track: &Track,
+        looping: bool,
+        start: Duration,
+    ) -> color_eyre::Result<(rodio::Sink, source::Position)> {
+        let sink = self.output.new_sink()?;
+        let offline = self.output.is_offline();
+
+        // the track was checked when it was loaded, so these shouldn't fail
+        let position = match &track.kind {
+            TrackKind::Sampled(data) => {
+                let (source, position) = TrackSource::new(data.clone(), looping)?;
+                append_from(&sink, source, start, offline);
+                position
+            }
+            TrackKind::Decoded(sound) => {
+                let loop_points = looping.then_some(loop_points::LoopPoints::WHOLE_TRACK);
+                let (source, position) = TrackSource::with_track(
+                    sound.clone(),
+                    sound.open().expect("decoded sounds can always be opened"),
+                    loop_points,
+                );
+                append_from(&sink, source, start, offline);
+                position
+            }
+            TrackKind::Midi { file, soundfont } => {
+                let (source, position) = MidiSource::new(soundfont, file, looping)?;
+                append_from(&sink, source, start, offline);
+                position
+            }
+        };
+
+        Ok((sink, position))
+    }
+
+    /// Pauses the BGM if an ME is playing, like rgss does. The BGM is resumed from the same spot afterwards.
+    fn after_play(&mut self, channel: Channel) {
+        if !matches!(channel, Channel::Bgm | Channel::Me) {
+            return;
+        }
+
+        let me_playing = self
+            .channels
+            .get(&Channel::Me)
+            .is_some_and(ChannelState::is_active);
+        if !me_playing {
+            return;
+        }
+
+        if let Some(bgm) = self.channels.get_mut(&Channel::Bgm) {
+            for stream in bgm.current.iter().chain(bgm.fading_out.iter()) {
+                stream.sink.pause();
+            }
+        }
+    }
+
+    fn resume_bgm(&mut self) {
+        if let Some(bgm) = self.channels.get_mut(&Channel::Bgm) {
+            for stream in bgm.current.iter().chain(bgm.fading_out.iter()) {
+                stream.sink.play();
+            }
+        }
+    }
+
+    /// Returns true if something needs to be updated regularly, even if no events arrive.
+    fn needs_tick(&self) -> bool {
+        // time only passes for the offline output when we're told to advance it
+        if self.output.is_offline() {
+            return false;
+        }
+        // the null output only plays when we tell it to
+        if self.output.needs_updates() && self.channels.values().any(ChannelState::is_active) {
+            return true;
+        }
+        // non looping channels have to be watched so they can be cleaned up (and the BGM resumed after an ME)
+        !self.se_voices.is_empty()
+            || self.channels.iter().any(|(channel, state)| {
+                state.needs_tick() || (!channel.is_looping() && state.is_active())
+            })
+    }
+
+    fn tick(&mut self) {
+        let now = self.output.now();
+        self.se_voices.remove_finished();
+
+        let mut me_finished = false;
+        for (&channel, state) in self.channels.iter_mut() {
+            let finished = state.tick(now);
+            me_finished |= channel == Channel::Me && finished;
+        }
+
+        if me_finished {
+            self.resume_bgm();
+        }
     }
 }
 
+/// Appends `source` to `sink`, starting `start` into it.
+///
+/// None of our sources can seek, so getting to `start` means decoding everything before it. For long tracks that
+/// takes a while, so it's done on another thread (with silence playing until it's done). The offline output
+/// skips right away instead, as how much silence plays would depend on how quickly the other thread runs.
+fn append_from<S>(sink: &rodio::Sink, mut source: S, start: Duration, offline: bool)
+where
+    S: Skip + Send + 'static,
+    S::Item: rodio::Sample + Send,
+    f32: rodio::cpal::FromSample<S::Item>,
+{
+    if start.is_zero() || offline {
+        source.skip_to(start);
+        sink.append(source);
+        return;
+    }
+
+    let (channels, sample_rate) = (source.channels(), source.sample_rate());
+    sink.append(Deferred::spawn(channels, sample_rate, move || {
+        source.skip_to(start);
+        source
+    }));
+}
+
 impl Audio {
     // Do we return a join handle as well?
     pub fn new(
         filesystem: Arc<FileSystem>,
+        config: AudioConfig,
     ) -> color_eyre::Result<(Self, JoinHandle<color_eyre::Result<()>>)> {
+        let soundfont_path = config
+            .soundfont
+            .clone()
+            .unwrap_or_else(|| midi::DEFAULT_SOUNDFONT_PATH.into());
+
+        let se_cache = SeCache::new(config.se_cache_size.unwrap_or(DEFAULT_SE_CACHE_SIZE));
+
+        let volume_path = config
+            .volume_path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_VOLUME_PATH.into());
+        let volumes = Volumes::load(&volume_path);
+
+        let offline = config.backend == AudioBackend::Offline;
         let (sender, receiver) = std::sync::mpsc::channel();
-        let join_handle = std::thread::spawn(|| audio_thread_fun(receiver, filesystem));
+        let (error_sender, errors) = crossbeam::channel::unbounded();
+        let thread_filesystem = filesystem.clone();
+        let join_handle = std::thread::spawn(move || {
+            audio_thread_fun(
+                receiver,
+                error_sender,
+                thread_filesystem,
+                config,
+                volumes,
+                volume_path,
+            )
+        });
+
+        let audio = Self {
+            sender,
+            filesystem,
+            soundfont_path,
+            soundfont: Mutex::new(None),
+            se_cache: Mutex::new(se_cache),
+            volumes: Mutex::new(volumes),
+            offline,
+            errors,
+        };
+        Ok((audio, join_handle))
+    }
+
+    /// Checks a track, so any problems are reported to the caller rather than on the audio thread.
+    ///
+    /// Only the start of the file is read here. Reading the rest is left to the audio thread.
+    fn check(&self, path: impl Into<Utf8PathBuf>) -> Result<TrackRequest> {
+        TrackRequest::check(&self.filesystem, path.into(), || self.soundfont())
+    }
+
+    /// Like [`Audio::load`], but sound effects are decoded up front and cached, since they're played so often.
+    fn load_se(&self, path: impl Into<Utf8PathBuf>) -> Result<Track> {
+        let path = path.into();
+        if let Some(sound) = self.se_cache.lock().get(&path) {
+            let kind = TrackKind::Decoded(sound);
+            return Ok(Track { path, kind });
+        }
+
+        let track = Track::load_decoded(&self.filesystem, path, || self.soundfont())?;
+        if let TrackKind::Decoded(sound) = &track.kind {
+            self.se_cache
+                .lock()
+                .insert(track.path.clone(), sound.clone());
+        }
+        Ok(track)
+    }
+
+    fn soundfont(&self) -> Result<Arc<SoundFont>> {
+        let mut soundfont = self.soundfont.lock();
+        if let Some(soundfont) = soundfont.as_ref() {
+            return Ok(soundfont.clone());
+        }
+
+        let loaded = track::load_soundfont(&self.filesystem, &self.soundfont_path)?;
+        *soundfont = Some(loaded.clone());
+        Ok(loaded)
+    }
 
-        Ok((Self { sender }, join_handle))
+    /// Sending only fails if the audio thread has exited (most likely because it errored), or a track played earlier
+    /// turned out to be broken (see [`Audio::take_error`]). The event is sent either way.
+    fn send(&self, event: Event) -> Result<()> {
+        // taken first, so errors are always from an earlier call rather than depending on how quickly this event is processed
+        let earlier = self.take_error();
+        self.sender.send(event).map_err(|_| Error::Disconnected)?;
+        earlier
+    }
+
+    /// Returns the first error the audio thread found while playing tracks that had already been checked,
+    /// like a file that is cut off after its header. These are reported on the next call, as the call that
+    /// played the track has already returned.
+    pub fn take_error(&self) -> Result<()> {
+        match self.errors.try_recv() {
+            Ok(error) => Err(error),
+            Err(_) => Ok(()),
+        }
     }
 }
 
-// TODO handle send error. these errors mean that the audio thread has exited and we should probably panic (or return an error)
 impl Audio {
-    pub fn bgm_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) {
+    pub fn channel_play(
+        &self,
+        channel: Channel,
+        path: impl Into<Utf8PathBuf>,
+        volume: u32,
+        pitch: u32,
+    ) -> Result<()> {
+        self.channel_play_from(channel, path, volume, pitch, Duration::ZERO, false)
+    }
+
+    /// Starts playing `start` into the track, optionally fading in (like RGSS3 does when resuming the BGM).
+    pub fn channel_play_from(
+        &self,
+        channel: Channel,
+        path: impl Into<Utf8PathBuf>,
+        volume: u32,
+        pitch: u32,
+        start: Duration,
+        fade_in: bool,
+    ) -> Result<()> {
         let play_args = PlayArgs {
-            path: path.into(),
+            track: self.check(path)?,
             volume,
             pitch,
+            start,
+            fade_in_on_offset: fade_in,
         };
-        let _ = self.sender.send(Event::PlayBGM(play_args));
+        self.send(Event::Play(channel, play_args))
     }
 
-    pub fn bgm_stop(&self) {
-        let _ = self.sender.send(Event::StopBGM);
+    pub fn channel_stop(&self, channel: Channel) -> Result<()> {
+        self.send(Event::Stop(channel))
     }
 
-    pub fn bgm_fade(&self, time: u32) {
-        let _ = self.sender.send(Event::FadeBGM(time));
+    /// Fades out the track playing on `channel` over `time` milliseconds, then stops it.
+    pub fn channel_fade(&self, channel: Channel, time: u32) -> Result<()> {
+        let duration = Duration::from_millis(time as u64);
+        self.send(Event::Fade(channel, duration))
     }
 
-    pub fn bgs_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) {
+    /// Fades out the track playing on `channel` while fading in a new one (starting `start` into the track),
+    /// both over `time` milliseconds.
+    pub fn channel_crossfade(
+        &self,
+        channel: Channel,
+        path: impl Into<Utf8PathBuf>,
+        volume: u32,
+        pitch: u32,
+        start: Duration,
+        time: u32,
+    ) -> Result<()> {
         let play_args = PlayArgs {
-            path: path.into(),
+            track: self.check(path)?,
             volume,
             pitch,
+            start,
+            fade_in_on_offset: false,
         };
-        let _ = self.sender.send(Event::PlayBGS(play_args));
+        let duration = Duration::from_millis(time as u64);
+        self.send(Event::Crossfade(channel, play_args, duration))
     }
 
-    pub fn bgs_stop(&self) {
-        let _ = self.sender.send(Event::StopBGS);
+    /// Asks the audio thread what `channel` is playing. Blocks until the audio thread responds.
+    ///
+    /// Returns `None` if nothing is playing.
+    pub fn channel_status(&self, channel: Channel) -> Result<Option<PlaybackStatus>> {
+        let (reply_sender, reply) = std::sync::mpsc::channel();
+        self.sender
+            .send(Event::Status(channel, reply_sender))
+            .map_err(|_| Error::Disconnected)?;
+        let status = reply.recv().map_err(|_| Error::Disconnected)?;
+        // everything sent before has been played by now, so any errors from it are waiting
+        self.take_error()?;
+        Ok(status)
     }
 
-    pub fn bgs_fade(&self, time: u32) {
-        let _ = self.sender.send(Event::FadeBGS(time));
+    pub fn bgm_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) -> Result<()> {
+        self.channel_play(Channel::Bgm, path, volume, pitch)
     }
 
-    pub fn me_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) {
-        let play_args = PlayArgs {
-            path: path.into(),
-            volume,
-            pitch,
-        };
-        let _ = self.sender.send(Event::PlayME(play_args));
+    pub fn bgm_stop(&self) -> Result<()> {
+        self.channel_stop(Channel::Bgm)
+    }
+
+    pub fn bgm_fade(&self, time: u32) -> Result<()> {
+        self.channel_fade(Channel::Bgm, time)
+    }
+
+    pub fn bgs_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) -> Result<()> {
+        self.channel_play(Channel::Bgs, path, volume, pitch)
+    }
+
+    pub fn bgs_stop(&self) -> Result<()> {
+        self.channel_stop(Channel::Bgs)
+    }
+
+    pub fn bgs_fade(&self, time: u32) -> Result<()> {
+        self.channel_fade(Channel::Bgs, time)
+    }
+
+    pub fn me_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) -> Result<()> {
+        self.channel_play(Channel::Me, path, volume, pitch)
+    }
+
+    pub fn me_stop(&self) -> Result<()> {
+        self.channel_stop(Channel::Me)
     }
 
-    pub fn me_stop(&self) {
-        let _ = self.sender.send(Event::StopME);
+    pub fn me_fade(&self, time: u32) -> Result<()> {
+        self.channel_fade(Channel::Me, time)
     }
 
-    pub fn me_fade(&self, time: u32) {
-        let _ = self.sender.send(Event::FadeME(time));
+    /// The position of the BGM, or zero if no BGM is playing.
+    pub fn bgm_pos(&self) -> Result<Duration> {
+        let status = self.channel_status(Channel::Bgm)?;
+        Ok(status.map_or(Duration::ZERO, |status| status.position))
     }
 
-    pub fn se_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) {
+    /// The position of the BGS, or zero if no BGS is playing.
+    pub fn bgs_pos(&self) -> Result<Duration> {
+        let status = self.channel_status(Channel::Bgs)?;
+        Ok(status.map_or(Duration::ZERO, |status| status.position))
+    }
+
+    /// Loads the SoundFont used for MIDI playback ahead of time, so the first MIDI track doesn't stutter.
+    pub fn setup_midi(&self) -> Result<()> {
+        self.soundfont().map(drop)
+    }
+
+    /// Advances the offline backend by `duration` (usually one frame). Time doesn't pass on its own when mixing offline,
+    /// so this is what keeps audio in step with the game. Other backends ignore this.
+    pub fn advance(&self, duration: Duration) -> Result<()> {
+        if !self.offline {
+            return Ok(());
+        }
+        self.send(Event::Advance(duration))
+    }
+
+    pub fn se_play(&self, path: impl Into<Utf8PathBuf>, volume: u32, pitch: u32) -> Result<()> {
         let play_args = PlayArgs {
-            path: path.into(),
+            track: self.load_se(path)?,
             volume,
             pitch,
+            start: Duration::ZERO,
+            fade_in_on_offset: false,
         };
-        let _ = self.sender.send(Event::PlaySE(play_args));
+        self.send(Event::PlaySE(play_args))
+    }
+
+    /// Stops every sound effect that is playing.
+    pub fn se_stop(&self) -> Result<()> {
+        self.send(Event::StopSE)
+    }
+}
+
+impl Audio {
+    /// The player's volume settings.
+    pub fn volumes(&self) -> Volumes {
+        *self.volumes.lock()
+    }
+
+    pub fn volume(&self, category: VolumeCategory) -> u32 {
+        self.volumes.lock().get(category)
     }
 
-    pub fn se_stop(&self) {
-        let _ = self.sender.send(Event::StopSE);
+    /// Sets a volume slider (0-100), applying it to everything that is playing.
+    ///
+    /// The audio thread saves it for next time, shortly after it stops changing (or when audio stops).
+    pub fn set_volume(&self, category: VolumeCategory, volume: u32) -> Result<()> {
+        let volumes = {
+            let mut volumes = self.volumes.lock();
+            volumes.set(category, volume);
+            *volumes
+        };
+        self.send(Event::SetVolumes(volumes))
     }
 }
 
 impl Audio {
+    /// Stops every channel and sound effect. Used when soft resetting.
+    pub fn stop_all(&self) -> Result<()> {
+        self.send(Event::StopAll)
+    }
+
     pub fn stop_processing(&self) {
+        // if the audio thread has already exited there's nothing to stop
         let _ = self.sender.send(Event::Exit);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `seconds` of a 440hz sine wave to `Audio/<name>.wav` in a fresh game directory.
+    fn game_dir(test: &str, tracks: &[(&str, f32)]) -> Utf8PathBuf {
+        let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sapphire-{test}-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("Audio")).unwrap();
+
+        for &(name, seconds) in tracks {
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: 44100,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let path = dir.join("Audio").join(format!("{name}.wav"));
+            let mut writer = hound::WavWriter::create(path, spec).unwrap();
+            for i in 0..(seconds * 44100.) as u32 {
+                let t = i as f32 / 44100.;
+                let sample = (t * 440. * std::f32::consts::TAU).sin() * i16::MAX as f32 * 0.5;
+                writer.write_sample(sample as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        dir
+    }
+
+    fn null_audio(dir: &Utf8Path) -> (Audio, JoinHandle<color_eyre::Result<()>>) {
+        let filesystem = Arc::new(FileSystem::new(dir, None).unwrap());
+        let config = AudioConfig {
+            backend: AudioBackend::Null,
+            volume_path: Some(dir.join("volume.toml")),
+            ..Default::default()
+        };
+        Audio::new(filesystem, config).unwrap()
+    }
+
+    /// The audio thread's state on a null output, and the clock that drives it.
+    fn null_state(dir: &Utf8Path) -> (AudioState, Arc<Mutex<Duration>>) {
+        let filesystem = Arc::new(FileSystem::new(dir, None).unwrap());
+        let now = Arc::new(Mutex::new(Duration::ZERO));
+        let output = Output::null_with_clock(output::Clock::Manual(now.clone()));
+        // errors are checked by looking at what's playing instead
+        let (errors, _) = crossbeam::channel::unbounded();
+        let state = AudioState::new(
+            output,
+            filesystem,
+            errors,
+            &AudioConfig::default(),
+            Volumes::default(),
+        );
+        (state, now)
+    }
+
+    fn play_args(state: &AudioState, path: &str, volume: u32) -> PlayArgs<TrackRequest> {
+        let track = TrackRequest::check(&state.filesystem, path.into(), || unreachable!()).unwrap();
+        PlayArgs {
+            track,
+            pitch: 100,
+            volume,
+            start: Duration::ZERO,
+            fade_in_on_offset: false,
+        }
+    }
+
+    fn play(state: &mut AudioState, channel: Channel, path: &str, volume: u32) {
+        let args = play_args(state, path, volume);
+        state.process(Event::Play(channel, args));
+    }
+
+    /// Moves the clock to `time`, and lets the audio thread catch up like it would after waking up.
+    fn run_until(state: &mut AudioState, now: &Mutex<Duration>, time: Duration) {
+        *now.lock() = time;
+        state.output.update();
+        state.tick();
+    }
+
+    fn status(state: &mut AudioState, channel: Channel) -> Option<PlaybackStatus> {
+        let (reply, receiver) = std::sync::mpsc::channel();
+        state.process(Event::Status(channel, reply));
+        receiver.recv().unwrap()
+    }
+
+    /// Plays a short scripted sequence on the offline backend, and returns the mixdown.
+    fn offline_mixdown(dir: &Utf8Path, name: &str, bgm_start: Duration) -> Vec<u8> {
+        let filesystem = Arc::new(FileSystem::new(dir, None).unwrap());
+        let mixdown_path = dir.join(name);
+        let config = AudioConfig {
+            backend: AudioBackend::Offline,
+            mixdown_path: Some(mixdown_path.clone()),
+            volume_path: Some(dir.join("volume.toml")),
+            ..Default::default()
+        };
+        let (audio, thread) = Audio::new(filesystem, config).unwrap();
+
+        let frame = Duration::from_secs(1) / 40;
+        audio
+            .channel_play_from(Channel::Bgm, "Audio/bgm.wav", 100, 100, bgm_start, false)
+            .unwrap();
+        for _ in 0..10 {
+            audio.advance(frame).unwrap();
+        }
+        audio.bgm_fade(100).unwrap();
+        audio.se_play("Audio/se.wav", 80, 150).unwrap();
+        for _ in 0..10 {
+            audio.advance(frame).unwrap();
+        }
+        audio.me_play("Audio/se.wav", 100, 100).unwrap();
+        for _ in 0..20 {
+            audio.advance(frame).unwrap();
+        }
+
+        audio.stop_processing();
+        thread.join().unwrap().unwrap();
+        std::fs::read(mixdown_path).unwrap()
+    }
+
+    #[test]
+    fn offline_mixdown_is_deterministic() {
+        let dir = game_dir("offline", &[("bgm", 1.0), ("se", 0.2)]);
+
+        let first = offline_mixdown(&dir, "first.wav", Duration::ZERO);
+        let second = offline_mixdown(&dir, "second.wav", Duration::ZERO);
+        assert!(first == second, "mixdowns differ");
+
+        let mut reader = hound::WavReader::new(first.as_slice()).unwrap();
+        // 40 frames at 40fps
+        assert_eq!(reader.duration(), 44100);
+        assert!(reader
+            .samples::<f32>()
+            .any(|sample| sample.unwrap().abs() > 0.1));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn offline_mixdown_from_a_position_is_deterministic() {
+        let dir = game_dir("offline-position", &[("bgm", 1.0), ("se", 0.2)]);
+
+        let start = Duration::from_millis(300);
+        let first = offline_mixdown(&dir, "first.wav", start);
+        let second = offline_mixdown(&dir, "second.wav", start);
+        assert!(first == second, "mixdowns differ");
+
+        // the BGM is heard from the first frame, rather than after however long the skip took
+        let mut reader = hound::WavReader::new(first.as_slice()).unwrap();
+        assert!(reader
+            .samples::<f32>()
+            .take(44100 / 40 * 2)
+            .any(|sample| sample.unwrap().abs() > 0.1));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn null_backend_advances_playback() {
+        let dir = game_dir("null-playback", &[("bgm", 1.0)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 80);
+        run_until(&mut state, &now, Duration::from_millis(250));
+
+        let bgm = status(&mut state, Channel::Bgm).unwrap();
+        assert_eq!(bgm.path, "Audio/bgm.wav");
+        assert_eq!(bgm.volume, 80);
+        // a little is buffered between the track and the mixer, so it lags slightly behind the clock
+        assert!(bgm.position > Duration::from_millis(200));
+        assert!(bgm.position <= Duration::from_millis(250));
+
+        state.process(Event::Stop(Channel::Bgm));
+        assert!(status(&mut state, Channel::Bgm).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_files_are_reported_to_the_caller() {
+        let dir = game_dir("bad-files", &[("bgm", 0.1)]);
+        std::fs::write(dir.join("Audio/garbage.ogg"), b"not audio at all").unwrap();
+        // passes the sniff, but there's nothing after the header
+        std::fs::write(dir.join("Audio/truncated.ogg"), b"OggS\0\x02\0\0\0\0\0\0").unwrap();
+        let (audio, thread) = null_audio(&dir);
+
+        let error = audio.bgm_play("Audio/missing.ogg", 100, 100).unwrap_err();
+        assert!(matches!(error, Error::NotFound(path) if path == "Audio/missing.ogg"));
+
+        let error = audio.se_play("Audio/garbage.ogg", 100, 100).unwrap_err();
+        assert!(matches!(error, Error::Decode { .. }));
+        let error = audio.bgm_play("Audio/garbage.ogg", 100, 100).unwrap_err();
+        assert!(matches!(error, Error::Decode { .. }));
+
+        // only the audio thread finds out, so it's reported on the next call
+        audio.bgm_play("Audio/truncated.ogg", 100, 100).unwrap();
+        let error = audio.channel_status(Channel::Bgm).unwrap_err();
+        assert!(matches!(error, Error::Decode { path, .. } if path == "Audio/truncated.ogg"));
+        assert!(audio.take_error().is_ok());
+
+        // and audio keeps working afterwards
+        audio.bgm_play("Audio/bgm.wav", 100, 100).unwrap();
+        assert!(audio.channel_status(Channel::Bgm).unwrap().is_some());
+
+        audio.stop_processing();
+        thread.join().unwrap().unwrap();
+        assert!(matches!(audio.bgm_stop(), Err(Error::Disconnected)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn volumes_are_saved() {
+        let dir = game_dir("volumes", &[("bgm", 1.0)]);
+
+        let (audio, thread) = null_audio(&dir);
+        audio.bgm_play("Audio/bgm.wav", 100, 100).unwrap();
+        audio.set_volume(VolumeCategory::Bgm, 40).unwrap();
+        audio.set_volume(VolumeCategory::Master, 250).unwrap();
+        assert_eq!(audio.volume(VolumeCategory::Master), 100);
+        // the track's own volume is unaffected
+        assert_eq!(
+            audio.channel_status(Channel::Bgm).unwrap().unwrap().volume,
+            100
+        );
+        audio.stop_processing();
+        thread.join().unwrap().unwrap();
+
+        let (audio, thread) = null_audio(&dir);
+        assert_eq!(audio.volume(VolumeCategory::Bgm), 40);
+        assert_eq!(audio.volume(VolumeCategory::Se), 100);
+        audio.stop_processing();
+        thread.join().unwrap().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn null_backend_finishes_me() {
+        let dir = game_dir("null-me", &[("bgm", 1.0), ("me", 0.1)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 100);
+        play(&mut state, Channel::Me, "Audio/me.wav", 100);
+        run_until(&mut state, &now, Duration::from_millis(50));
+        assert!(status(&mut state, Channel::Me).is_some());
+        // the BGM is paused while the ME plays
+        let paused_at = status(&mut state, Channel::Bgm).unwrap().position;
+        assert_eq!(paused_at, Duration::ZERO);
+
+        run_until(&mut state, &now, Duration::from_millis(350));
+        assert!(status(&mut state, Channel::Me).is_none());
+        assert!(status(&mut state, Channel::Bgm).is_some());
+
+        // and resumed afterwards
+        run_until(&mut state, &now, Duration::from_millis(450));
+        assert!(status(&mut state, Channel::Bgm).unwrap().position > paused_at);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// The volume the channel's current track is playing at.
+    fn sink_volume(state: &AudioState, channel: Channel) -> f32 {
+        state.channels[&channel]
+            .current
+            .as_ref()
+            .unwrap()
+            .sink
+            .volume()
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn fades_follow_a_linear_envelope() {
+        let dir = game_dir("null-fade", &[("bgm", 1.0)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 80);
+        let full = sink_volume(&state, Channel::Bgm);
+        state.process(Event::Fade(Channel::Bgm, Duration::from_millis(1000)));
+
+        for (millis, envelope) in [(250, 0.75), (500, 0.5), (900, 0.1)] {
+            run_until(&mut state, &now, Duration::from_millis(millis));
+            assert_near(sink_volume(&state, Channel::Bgm), full * envelope);
+        }
+        // the track's own volume is unaffected
+        assert_eq!(status(&mut state, Channel::Bgm).unwrap().volume, 80);
+
+        // and the track stops once it's silent
+        run_until(&mut state, &now, Duration::from_millis(1000));
+        assert!(status(&mut state, Channel::Bgm).is_none());
+        assert!(!state.needs_tick());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replaying_cancels_a_fade() {
+        let dir = game_dir("null-fade-replay", &[("bgm", 1.0)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 100);
+        let full = sink_volume(&state, Channel::Bgm);
+        state.process(Event::Fade(Channel::Bgm, Duration::from_millis(200)));
+        run_until(&mut state, &now, Duration::from_millis(100));
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 100);
+        assert_near(sink_volume(&state, Channel::Bgm), full);
+        run_until(&mut state, &now, Duration::from_millis(300));
+        assert!(status(&mut state, Channel::Bgm).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bgm_fades_while_paused_for_an_me() {
+        let dir = game_dir("null-fade-me", &[("bgm", 1.0), ("me", 1.0)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/bgm.wav", 100);
+        let full = sink_volume(&state, Channel::Bgm);
+        play(&mut state, Channel::Me, "Audio/me.wav", 100);
+        state.process(Event::Fade(Channel::Bgm, Duration::from_millis(200)));
+
+        run_until(&mut state, &now, Duration::from_millis(100));
+        assert_near(sink_volume(&state, Channel::Bgm), full * 0.5);
+        assert!(state.channels[&Channel::Bgm]
+            .current
+            .as_ref()
+            .unwrap()
+            .sink
+            .is_paused());
+
+        // the fade finishes while the ME is still playing, so there's nothing to resume afterwards
+        run_until(&mut state, &now, Duration::from_millis(250));
+        assert!(status(&mut state, Channel::Bgm).is_none());
+        assert!(status(&mut state, Channel::Me).is_some());
+
+        run_until(&mut state, &now, Duration::from_millis(1250));
+        assert!(status(&mut state, Channel::Me).is_none());
+        assert!(status(&mut state, Channel::Bgm).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn crossfades_swap_tracks() {
+        let dir = game_dir("null-crossfade", &[("old", 1.0), ("new", 1.0)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Bgm, "Audio/old.wav", 100);
+        let full = sink_volume(&state, Channel::Bgm);
+        let args = play_args(&state, "Audio/new.wav", 100);
+        state.process(Event::Crossfade(
+            Channel::Bgm,
+            args,
+            Duration::from_millis(200),
+        ));
+        // the new track is the one that's playing straight away
+        assert_eq!(
+            status(&mut state, Channel::Bgm).unwrap().path,
+            "Audio/new.wav"
+        );
+        assert_near(sink_volume(&state, Channel::Bgm), 0.0);
+
+        run_until(&mut state, &now, Duration::from_millis(50));
+        let bgm = &state.channels[&Channel::Bgm];
+        assert_eq!(bgm.fading_out.len(), 1);
+        assert_eq!(bgm.fading_out[0].path, "Audio/old.wav");
+        assert_near(bgm.fading_out[0].sink.volume(), full * 0.75);
+        assert_near(sink_volume(&state, Channel::Bgm), full * 0.25);
+
+        run_until(&mut state, &now, Duration::from_millis(200));
+        assert!(state.channels[&Channel::Bgm].fading_out.is_empty());
+        assert_near(sink_volume(&state, Channel::Bgm), full);
+        assert!(!state.channels[&Channel::Bgm].needs_tick());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_channels_are_independent() {
+        let dir = game_dir("null-numbered", &[("long", 1.0), ("short", 0.1)]);
+        let (mut state, now) = null_state(&dir);
+
+        play(&mut state, Channel::Numbered(1), "Audio/short.wav", 100);
+        play(&mut state, Channel::Numbered(2), "Audio/long.wav", 100);
+        play(
+            &mut state,
+            Channel::LoopingNumbered(1),
+            "Audio/short.wav",
+            100,
+        );
+        play(&mut state, Channel::Bgm, "Audio/long.wav", 100);
+        let full = sink_volume(&state, Channel::Numbered(2));
+
+        state.process(Event::Stop(Channel::Numbered(2)));
+        state.process(Event::Fade(Channel::Bgm, Duration::from_millis(1000)));
+        assert!(status(&mut state, Channel::Numbered(2)).is_none());
+        assert!(status(&mut state, Channel::Numbered(1)).is_some());
+        assert!(status(&mut state, Channel::LoopingNumbered(1)).is_some());
+
+        // the one-shot channel finishes, while the looping channel with the same number keeps going
+        run_until(&mut state, &now, Duration::from_millis(350));
+        assert!(status(&mut state, Channel::Numbered(1)).is_none());
+        assert!(status(&mut state, Channel::LoopingNumbered(1)).is_some());
+        // and only the BGM is fading
+        assert_near(sink_volume(&state, Channel::LoopingNumbered(1)), full);
+        assert!(sink_volume(&state, Channel::Bgm) < full);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
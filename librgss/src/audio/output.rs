@@ -0,0 +1,357 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use camino::Utf8Path;
+use color_eyre::eyre::WrapErr;
+use parking_lot::Mutex;
+use rodio::dynamic_mixer::{DynamicMixer, DynamicMixerController};
+
+use super::device::{DeviceStream, DeviceWatcher};
+use crate::config::AudioBackend;
+
+pub(super) const MIXER_CHANNELS: u16 = 2;
+pub(super) const MIXER_SAMPLE_RATE: u32 = 44100;
+/// How often to check whether the output device has changed (or come back).
+pub(super) const DEVICE_POLL: Duration = Duration::from_secs(2);
+
+/// Where the audio thread sends the audio it plays.
+pub(super) enum Output {
+    /// An output device. If the device goes away, playback carries on as if it were the null output
+    /// until a device can be opened again.
+    Device {
+        /// `None` while there's no device to play on.
+        stream: Option<DeviceStream>,
+        mixer: Arc<Mutex<Mixer>>,
+        /// The device named in the config, or `None` for the default device.
+        wanted: Option<String>,
+        watcher: DeviceWatcher,
+        started: Instant,
+        last_poll: Instant,
+    },
+    /// No output device. Audio is still mixed (and thrown away) in real time, so playback behaves as it would on a device.
+    Null { mixer: Mixer, clock: Clock },
+    /// Mixes into a WAV file. Time only passes when [`Output::advance`] is called, so the same events always produce the same file.
+    Offline {
+        mixer: Mixer,
+        writer: hound::WavWriter<BufWriter<File>>,
+        elapsed: Duration,
+    },
+}
+
+/// Where the null output gets the time from.
+#[derive(Clone)]
+pub(super) enum Clock {
+    Real(Instant),
+    /// Set by hand, so tests don't depend on how quickly they run.
+    #[cfg(test)]
+    Manual(Arc<Mutex<Duration>>),
+}
+
+impl Clock {
+    fn elapsed(&self) -> Duration {
+        match self {
+            Self::Real(started) => started.elapsed(),
+            #[cfg(test)]
+            Self::Manual(now) => *now.lock(),
+        }
+    }
+}
+
+/// A software mixer, for when there's no device to mix for us.
+pub(super) struct Mixer {
+    controller: Arc<DynamicMixerController<f32>>,
+    mixer: DynamicMixer<f32>,
+    /// Frames mixed so far.
+    frames: u64,
+}
+
+impl Output {
+    /// Opens the output selected by the config.
+    ///
+    /// Falls back to the null output if there is no output device.
+    pub fn new(
+        backend: AudioBackend,
+        device: Option<&str>,
+        mixdown_path: &Utf8Path,
+    ) -> color_eyre::Result<Self> {
+        match backend {
+            AudioBackend::Auto => Ok(Self::open_device(device).unwrap_or_else(|error| {
+                eprintln!("{error:?}");
+                println!("Falling back to null audio output");
+                Self::null()
+            })),
+            AudioBackend::Null => {
+                println!("Using null audio output");
+                Ok(Self::null())
+            }
+            AudioBackend::Offline => {
+                println!("Mixing audio to {mixdown_path}");
+                Self::offline(mixdown_path)
+            }
+        }
+    }
+
+    fn open_device(wanted: Option<&str>) -> color_eyre::Result<Self> {
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let stream = DeviceStream::open(wanted, &mixer)?;
+        Ok(Self::Device {
+            stream: Some(stream),
+            mixer,
+            wanted: wanted.map(str::to_string),
+            watcher: DeviceWatcher::spawn(wanted.map(str::to_string), DEVICE_POLL),
+            started: Instant::now(),
+            last_poll: Instant::now(),
+        })
+    }
+
+    pub fn null() -> Self {
+        Self::null_with_clock(Clock::Real(Instant::now()))
+    }
+
+    pub fn null_with_clock(clock: Clock) -> Self {
+        Self::Null {
+            mixer: Mixer::new(),
+            clock,
+        }
+    }
+
+    pub fn offline(path: &Utf8Path) -> color_eyre::Result<Self> {
+        // write the mixer's output as-is, so nothing is lost to quantization
+        let spec = hound::WavSpec {
+            channels: MIXER_CHANNELS,
+            sample_rate: MIXER_SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .wrap_err_with(|| format!("failed to create {path}"))?;
+
+        Ok(Self::Offline {
+            mixer: Mixer::new(),
+            writer,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    /// Whether this output only plays when [`Output::update`] is called.
+    pub fn needs_updates(&self) -> bool {
+        matches!(self, Self::Null { .. } | Self::Device { stream: None, .. })
+    }
+
+    /// How often [`Output::update`] should be called to notice device changes, even if nothing else is happening.
+    pub fn poll_interval(&self) -> Option<Duration> {
+        matches!(self, Self::Device { .. }).then_some(DEVICE_POLL)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Offline { .. })
+    }
+
+    /// The current playback time, which effects (like fades) are timed against.
+    pub fn now(&self) -> Duration {
+        match self {
+            Self::Device { started, .. } => started.elapsed(),
+            Self::Null { clock, .. } => clock.elapsed(),
+            Self::Offline { elapsed, .. } => *elapsed,
+        }
+    }
+
+    pub fn new_sink(&self) -> Result<rodio::Sink, rodio::PlayError> {
+        match self {
+            Self::Device { mixer, .. } => Ok(mixer.lock().new_sink()),
+            Self::Null { mixer, .. } | Self::Offline { mixer, .. } => Ok(mixer.new_sink()),
+        }
+    }
+
+    /// Advances playback on the null output to the current time.
+    ///
+    /// Devices play on their own, but this is where they are reopened if they fail or the preferred device changes.
+    pub fn update(&mut self) {
+        match self {
+            Self::Null { mixer, clock } => mixer.skip_until(clock.elapsed()),
+            Self::Device {
+                stream,
+                mixer,
+                wanted,
+                watcher,
+                started,
+                last_poll,
+            } => {
+                if stream.as_ref().is_some_and(DeviceStream::failed) {
+                    println!("Lost audio device, playing without one until it comes back");
+                    *stream = None;
+                }
+                if stream.is_none() {
+                    mixer.lock().skip_until(started.elapsed());
+                }
+                watcher.set_searching(stream.is_none());
+
+                if last_poll.elapsed() < DEVICE_POLL {
+                    return;
+                }
+                *last_poll = Instant::now();
+
+                let current = stream.as_ref().map(|stream| stream.name.as_str());
+                if !should_switch(current, watcher.preferred().as_deref()) {
+                    return;
+                }
+                // the old stream has to be closed first, or both would pull from the mixer
+                *stream = None;
+                match DeviceStream::open(wanted.as_deref(), mixer) {
+                    Ok(new) => *stream = Some(new),
+                    Err(error) => eprintln!("{error:?}"),
+                }
+            }
+            Self::Offline { .. } => {}
+        }
+    }
+
+    /// Mixes `duration` more audio into the offline output. Does nothing for other outputs, which play in real time.
+    pub fn advance(&mut self, duration: Duration) -> color_eyre::Result<()> {
+        if let Self::Offline {
+            mixer,
+            writer,
+            elapsed,
+        } = self
+        {
+            *elapsed += duration;
+            mixer.mix_until(*elapsed, |sample| writer.write_sample(sample))?;
+        }
+        Ok(())
+    }
+
+    /// Finishes writing the offline output.
+    pub fn finish(self) -> color_eyre::Result<()> {
+        if let Self::Offline { writer, .. } = self {
+            writer.finalize()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether to (re)open the device stream, given the device it's playing on (if any) and the device it should be.
+fn should_switch(current: Option<&str>, preferred: Option<&str>) -> bool {
+    preferred.is_some() && preferred != current
+}
+
+impl Mixer {
+    pub(super) fn new() -> Self {
+        let (controller, mixer) = rodio::dynamic_mixer::mixer(MIXER_CHANNELS, MIXER_SAMPLE_RATE);
+        Self {
+            controller,
+            mixer,
+            frames: 0,
+        }
+    }
+
+    pub(super) fn new_sink(&self) -> rodio::Sink {
+        let (sink, queue) = rodio::Sink::new_idle();
+        self.controller.add(queue);
+        sink
+    }
+
+    /// Mixes the next samples into `block`, as an output device would.
+    pub(super) fn fill(&mut self, block: &mut [f32]) {
+        for sample in block.iter_mut() {
+            *sample = self.mixer.next().unwrap_or(0.0);
+        }
+        self.frames += (block.len() / MIXER_CHANNELS as usize) as u64;
+    }
+
+    /// Returns how many samples need to be mixed to reach `time`.
+    fn samples_until(&mut self, time: Duration) -> u64 {
+        let target = (time.as_nanos() * MIXER_SAMPLE_RATE as u128 / 1_000_000_000) as u64;
+        let frames = target.saturating_sub(self.frames);
+        self.frames = target;
+        frames * MIXER_CHANNELS as u64
+    }
+
+    /// Mixes everything up to `time`, passing each sample to `output`.
+    fn mix_until<E>(
+        &mut self,
+        time: Duration,
+        mut output: impl FnMut(f32) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for _ in 0..self.samples_until(time) {
+            // nothing is playing, which is just silence
+            let sample = self.mixer.next().unwrap_or(0.0);
+            output(sample)?;
+        }
+        Ok(())
+    }
+
+    /// Mixes (and throws away) everything up to `time`.
+    fn skip_until(&mut self, time: Duration) {
+        for _ in 0..self.samples_until(time) {
+            // nothing is playing, so there's nothing to catch up on
+            if self.mixer.next().is_none() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::Source;
+
+    use super::*;
+
+    #[test]
+    fn switches_devices_only_when_needed() {
+        // lost the device, and there's another one
+        assert!(should_switch(None, Some("headphones")));
+        // the preferred device changed
+        assert!(should_switch(Some("speakers"), Some("headphones")));
+        // nothing changed
+        assert!(!should_switch(Some("speakers"), Some("speakers")));
+        // nothing to switch to, so keep what we have (or carry on without)
+        assert!(!should_switch(Some("speakers"), None));
+        assert!(!should_switch(None, None));
+    }
+
+    #[test]
+    fn plays_without_a_device() {
+        let mut output = Output::Device {
+            stream: None,
+            mixer: Arc::new(Mutex::new(Mixer::new())),
+            wanted: None,
+            watcher: DeviceWatcher::spawn_with(DEVICE_POLL, |_| None),
+            started: Instant::now() - Duration::from_secs(1),
+            last_poll: Instant::now(),
+        };
+        assert!(output.needs_updates());
+
+        let sink = output.new_sink().unwrap();
+        sink.append(rodio::source::SineWave::new(440.0).take_duration(Duration::from_millis(500)));
+        output.update();
+
+        let Output::Device { mixer, .. } = &output else {
+            unreachable!()
+        };
+        // mixed up to the current time, as the null output would
+        assert!(mixer.lock().frames >= MIXER_SAMPLE_RATE as u64);
+        assert!(sink.empty());
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, io::Cursor, sync::Arc, time::Duration};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use rodio::{decoder::DecoderError, Decoder, Source};
+
+/// A fully decoded sound effect, so playing it again doesn't have to touch the disk or decode anything.
+pub(super) struct Sound {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+}
+
+impl Sound {
+    pub fn decode(data: Vec<u8>) -> Result<Self, DecoderError> {
+        let decoder = Decoder::new(Cursor::new(data))?;
+        let channels = decoder.channels().max(1);
+        let sample_rate = decoder.sample_rate();
+        let samples = decoder.collect();
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            samples,
+        })
+    }
+
+    /// How much memory the decoded samples take up.
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(&*self.samples)
+    }
+}
+
+/// Plays a [`Sound`] without copying it.
+pub(super) struct SoundSource {
+    sound: Arc<Sound>,
+    index: usize,
+}
+
+impl super::source::Track for Arc<Sound> {
+    type Source = SoundSource;
+
+    fn open(&self) -> Option<Self::Source> {
+        Some(SoundSource {
+            sound: self.clone(),
+            index: 0,
+        })
+    }
+}
+
+impl Iterator for SoundSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = *self.sound.samples.get(self.index)?;
+        self.index += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.sound.samples.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl Source for SoundSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.sound.samples.len() - self.index)
+    }
+
+    fn channels(&self) -> u16 {
+        self.sound.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.sound.samples.len() / self.sound.channels as usize;
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.sound.sample_rate as f64,
+        ))
+    }
+}
+
+/// Keeps recently played sound effects decoded, up to a memory budget.
+/// The least recently played ones are dropped first.
+pub(super) struct SeCache {
+    budget: usize,
+    used: usize,
+    /// Bumped every time a sound is used, so we know which one was used least recently.
+    clock: u64,
+    entries: HashMap<Utf8PathBuf, Entry>,
+}
+
+struct Entry {
+    sound: Arc<Sound>,
+    last_used: u64,
+}
+
+impl SeCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, path: &Utf8Path) -> Option<Arc<Sound>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.last_used = self.clock;
+        Some(entry.sound.clone())
+    }
+
+    /// Caches `sound`, dropping the least recently used sounds to make room for it.
+    ///
+    /// Sounds bigger than the whole budget aren't cached at all.
+    pub fn insert(&mut self, path: Utf8PathBuf, sound: Arc<Sound>) {
+        let size = sound.size();
+        if size > self.budget {
+            return;
+        }
+
+        self.clock += 1;
+        let entry = Entry {
+            sound,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(path, entry) {
+            self.used -= old.sound.size();
+        }
+        self.used += size;
+
+        while self.used > self.budget {
+            self.evict();
+        }
+    }
+
+    fn evict(&mut self) {
+        let Some(path) = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(path, _)| path.clone())
+        else {
+            return;
+        };
+        if let Some(entry) = self.entries.remove(&path) {
+            self.used -= entry.sound.size();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(samples: usize) -> Arc<Sound> {
+        Arc::new(Sound {
+            channels: 1,
+            sample_rate: 44100,
+            samples: vec![0; samples].into(),
+        })
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        // room for two 100 sample sounds
+        let mut cache = SeCache::new(400);
+        cache.insert("a".into(), sound(100));
+        cache.insert("b".into(), sound(100));
+        assert!(cache.get("a".into()).is_some());
+
+        cache.insert("c".into(), sound(100));
+        assert!(cache.get("a".into()).is_some());
+        assert!(cache.get("b".into()).is_none());
+        assert!(cache.get("c".into()).is_some());
+        assert_eq!(cache.used, 400);
+    }
+
+    #[test]
+    fn skips_sounds_over_budget() {
+        let mut cache = SeCache::new(400);
+        cache.insert("a".into(), sound(100));
+        cache.insert("huge".into(), sound(1000));
+        assert!(cache.get("huge".into()).is_none());
+        assert!(cache.get("a".into()).is_some());
+    }
+
+    #[test]
+    fn replacing_keeps_size_accurate() {
+        let mut cache = SeCache::new(400);
+        cache.insert("a".into(), sound(100));
+        cache.insert("a".into(), sound(50));
+        assert_eq!(cache.used, 100);
+    }
+}
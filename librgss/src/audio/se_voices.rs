@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use rodio::Sink;
+
+use crate::SeEviction;
+
+/// The sound effects that are currently playing, limited to a maximum number of voices.
+pub(super) struct SeVoices {
+    /// In the order they were started.
+    voices: Vec<Voice>,
+    max: usize,
+    eviction: SeEviction,
+    /// The gain from the player's volume sliders.
+    mix: f32,
+}
+
+struct Voice {
+    sink: Sink,
+    volume: u32,
+}
+
+impl SeVoices {
+    pub fn new(max: usize, eviction: SeEviction, mix: f32) -> Self {
+        Self {
+            voices: Vec::with_capacity(max),
+            max,
+            eviction,
+            mix,
+        }
+    }
+
+    /// Makes room for a new sound effect played at `volume`, stopping another one if needed.
+    ///
+    /// Returns false if the new sound effect shouldn't be played.
+    pub fn make_room(&mut self, volume: u32) -> bool {
+        self.remove_finished();
+        if self.voices.len() < self.max {
+            return true;
+        }
+
+        let evicted = match self.eviction {
+            SeEviction::Oldest => (!self.voices.is_empty()).then_some(0),
+            SeEviction::Quietest => self
+                .voices
+                .iter()
+                .enumerate()
+                // the first (oldest) of equally quiet voices
+                .min_by_key(|(index, voice)| (voice.volume, *index))
+                .filter(|(_, voice)| voice.volume <= volume)
+                .map(|(index, _)| index),
+            SeEviction::Reject => None,
+        };
+
+        match evicted {
+            Some(index) => {
+                self.voices.remove(index).sink.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn push(&mut self, sink: Sink, volume: u32) {
+        sink.set_volume(volume as f32 / 100. * self.mix);
+        self.voices.push(Voice { sink, volume });
+    }
+
+    /// Applies a new gain from the volume sliders to every playing sound effect.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix;
+        for voice in &self.voices {
+            voice.sink.set_volume(voice.volume as f32 / 100. * mix);
+        }
+    }
+
+    pub fn remove_finished(&mut self) {
+        self.voices.retain(|voice| !voice.sink.empty());
+    }
+
+    pub fn stop_all(&mut self) {
+        for voice in self.voices.drain(..) {
+            voice.sink.stop();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::source::{SineWave, Source};
+    use std::time::Duration;
+
+    /// A sink that won't finish, since nothing is pulling samples from it.
+    fn playing_sink() -> Sink {
+        let (sink, _queue) = Sink::new_idle();
+        sink.append(SineWave::new(440.).take_duration(Duration::from_secs(1)));
+        sink
+    }
+
+    fn voices(eviction: SeEviction, volumes: &[u32]) -> SeVoices {
+        let mut voices = SeVoices::new(volumes.len(), eviction, 1.0);
+        for &volume in volumes {
+            assert!(voices.make_room(volume));
+            voices.push(playing_sink(), volume);
+        }
+        voices
+    }
+
+    fn volumes(voices: &SeVoices) -> Vec<u32> {
+        voices.voices.iter().map(|voice| voice.volume).collect()
+    }
+
+    #[test]
+    fn oldest_is_evicted() {
+        let mut voices = voices(SeEviction::Oldest, &[10, 20, 30]);
+        assert!(voices.make_room(5));
+        assert_eq!(volumes(&voices), [20, 30]);
+    }
+
+    #[test]
+    fn quietest_is_evicted() {
+        let mut voices = voices(SeEviction::Quietest, &[50, 20, 20, 80]);
+        assert!(voices.make_room(60));
+        assert_eq!(volumes(&voices), [50, 20, 80]);
+        // quieter than everything playing
+        voices.push(playing_sink(), 60);
+        assert!(!voices.make_room(10));
+    }
+
+    #[test]
+    fn reject_keeps_playing_voices() {
+        let mut voices = voices(SeEviction::Reject, &[10, 20]);
+        assert!(!voices.make_room(100));
+        assert_eq!(volumes(&voices), [10, 20]);
+    }
+
+    #[test]
+    fn mix_applies_to_playing_voices() {
+        let mut voices = voices(SeEviction::Oldest, &[50, 100]);
+        voices.set_mix(0.5);
+        let gains: Vec<f32> = voices
+            .voices
+            .iter()
+            .map(|voice| voice.sink.volume())
+            .collect();
+        assert_eq!(gains, [0.25, 0.5]);
+    }
+
+    #[test]
+    fn stop_all_clears_voices() {
+        let mut voices = voices(SeEviction::Oldest, &[10, 20]);
+        voices.stop_all();
+        assert!(voices.is_empty());
+        assert!(voices.make_room(10));
+    }
+}
@@ -0,0 +1,518 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    io::Cursor,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{Receiver, TryRecvError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{decoder::DecoderError, Decoder, Sample, Source};
+
+use super::loop_points::LoopPoints;
+
+/// Something [`TrackSource`] can (re)start decoding from the beginning.
+pub(super) trait Track {
+    type Source: Source<Item = i16>;
+
+    fn open(&self) -> Option<Self::Source>;
+}
+
+impl Track for Arc<[u8]> {
+    type Source = Decoder<Cursor<Arc<[u8]>>>;
+
+    fn open(&self) -> Option<Self::Source> {
+        Decoder::new(Cursor::new(self.clone())).ok()
+    }
+}
+
+/// A source that can only get partway in by decoding (and throwing away) everything before that point.
+pub(super) trait Skip: Source
+where
+    Self::Item: Sample,
+{
+    fn skip_to(&mut self, start: Duration);
+}
+
+/// A decoded track that keeps track of its playback position, and (optionally) loops.
+///
+/// Unlike [`rodio::decoder::LoopedDecoder`] we keep the encoded file around so we can restart decoding,
+/// which is what lets us know where in the track we are and loop somewhere other than the start.
+pub(super) struct TrackSource<T: Track = Arc<[u8]>> {
+    track: T,
+    playback: Playback<T::Source>,
+    /// `None` if the track doesn't loop.
+    loop_points: Option<LoopPoints>,
+    channels: u16,
+    sample_rate: u32,
+    /// Samples (not frames!) into the track.
+    samples: u64,
+    position: Position,
+}
+
+enum Playback<S> {
+    Decoding {
+        source: S,
+        /// The loop region, recorded on the way through so we don't have to decode the intro again.
+        /// `None` if the loop starts at the beginning of the track.
+        recording: Option<Vec<i16>>,
+    },
+    /// Playing the recorded loop region.
+    Recorded {
+        samples: Vec<i16>,
+        index: usize,
+    },
+    Finished,
+}
+
+/// A handle to the playback position of a source, which is updated from the output thread.
+#[derive(Clone)]
+pub(super) struct Position {
+    frames: Arc<AtomicU64>,
+    sample_rate: u32,
+}
+
+impl TrackSource {
+    /// Opens a track, reading its loop points if it loops.
+    pub fn new(data: Arc<[u8]>, looping: bool) -> Result<(Self, Position), DecoderError> {
+        let source = Decoder::new(Cursor::new(data.clone()))?;
+        let loop_points =
+            looping.then(|| LoopPoints::read(&data).unwrap_or(LoopPoints::WHOLE_TRACK));
+
+        Ok(Self::with_track(data, source, loop_points))
+    }
+}
+
+impl<T: Track> TrackSource<T> {
+    pub fn with_track(
+        track: T,
+        source: T::Source,
+        loop_points: Option<LoopPoints>,
+    ) -> (Self, Position) {
+        let channels = source.channels().max(1);
+        let sample_rate = source.sample_rate();
+
+        let position = Position::new(sample_rate);
+        let recording = loop_points
+            .filter(|loop_points| loop_points.start > 0)
+            .map(|_| Vec::new());
+        let this = Self {
+            track,
+            playback: Playback::Decoding { source, recording },
+            loop_points,
+            channels,
+            sample_rate,
+            samples: 0,
+            position: position.clone(),
+        };
+
+        (this, position)
+    }
+
+    fn loop_start(&self) -> Option<u64> {
+        self.loop_points
+            .map(|loop_points| loop_points.start * self.channels as u64)
+    }
+
+    fn at_loop_end(&self) -> bool {
+        self.loop_points
+            .and_then(|loop_points| loop_points.end())
+            .is_some_and(|end| self.samples >= end * self.channels as u64)
+    }
+
+    /// Jumps back to the loop start. Returns `None` if the track doesn't loop (or can't be restarted).
+    fn wrap(&mut self) -> Option<()> {
+        let loop_start = self.loop_start()?;
+
+        self.playback = match std::mem::replace(&mut self.playback, Playback::Finished) {
+            Playback::Decoding {
+                recording: Some(samples),
+                ..
+            }
+            | Playback::Recorded { samples, .. } => Playback::Recorded { samples, index: 0 },
+            Playback::Decoding { .. } | Playback::Finished => {
+                let mut source = self.track.open()?;
+                for _ in 0..loop_start {
+                    source.next()?;
+                }
+                let recording = (loop_start > 0).then(Vec::new);
+                Playback::Decoding { source, recording }
+            }
+        };
+        self.samples = loop_start;
+
+        Some(())
+    }
+
+    fn next_sample(&mut self) -> Option<i16> {
+        let loop_start = self.loop_start();
+        match &mut self.playback {
+            Playback::Decoding { source, recording } => {
+                let sample = source.next()?;
+                if let Some(recording) = recording {
+                    if loop_start.is_some_and(|start| self.samples >= start) {
+                        recording.push(sample);
+                    }
+                }
+                Some(sample)
+            }
+            Playback::Recorded { samples, index } => {
+                let sample = *samples.get(*index)?;
+                *index += 1;
+                Some(sample)
+            }
+            Playback::Finished => None,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.samples += 1;
+        let channels = self.channels as u64;
+        if self.samples.is_multiple_of(channels) {
+            self.position.set(self.samples / channels);
+        }
+    }
+}
+
+impl<T: Track> Skip for TrackSource<T> {
+    fn skip_to(&mut self, start: Duration) {
+        let skipped_samples =
+            (start.as_secs_f64() * self.sample_rate as f64) as u64 * self.channels as u64;
+        for _ in 0..skipped_samples {
+            if self.next().is_none() {
+                break;
+            }
+        }
+    }
+}
+
+impl<T: Track> Iterator for TrackSource<T> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.at_loop_end() {
+            self.wrap()?;
+        }
+
+        let sample = match self.next_sample() {
+            Some(sample) => sample,
+            // the loop end is past the end of the track (or there isn't one)
+            None => {
+                self.wrap()?;
+                // an empty loop would otherwise loop forever
+                self.next_sample()?
+            }
+        };
+
+        self.advance();
+        Some(sample)
+    }
+}
+
+impl<T: Track> Source for TrackSource<T> {
+    fn current_frame_len(&self) -> Option<usize> {
+        // looping can happen partway through a frame, but the channels and sample rate never change
+        match &self.playback {
+            Playback::Decoding { source, .. } if self.loop_points.is_none() => {
+                source.current_frame_len()
+            }
+            _ => None,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match &self.playback {
+            Playback::Decoding { source, .. } if self.loop_points.is_none() => {
+                source.total_duration()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Plays silence until a source being prepared on another thread is ready.
+///
+/// This keeps slow work (like skipping to a start position) off the audio thread, so other events aren't held up.
+pub(super) struct Deferred<S> {
+    receiver: Receiver<S>,
+    source: Option<S>,
+    channels: u16,
+    sample_rate: u32,
+    /// Silence is played a whole frame at a time, so the channels line up when the source arrives.
+    frame_index: u16,
+}
+
+impl<S> Deferred<S>
+where
+    S: Source + Send + 'static,
+    S::Item: Sample,
+{
+    /// Starts running `prepare` on another thread. The prepared source must have `channels` and `sample_rate`.
+    pub fn spawn(
+        channels: u16,
+        sample_rate: u32,
+        prepare: impl FnOnce() -> S + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("audio seek".to_string())
+            .spawn(move || {
+                // the sink might have been stopped in the meantime
+                let _ = sender.send(prepare());
+            })
+            .expect("failed to start audio seek thread");
+
+        Self {
+            receiver,
+            source: None,
+            channels: channels.max(1),
+            sample_rate,
+            frame_index: 0,
+        }
+    }
+}
+
+impl<S> Iterator for Deferred<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.source.is_none() && self.frame_index == 0 {
+            match self.receiver.try_recv() {
+                Ok(source) => self.source = Some(source),
+                Err(TryRecvError::Empty) => {}
+                // preparing the source panicked
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+        if let Some(source) = &mut self.source {
+            return source.next();
+        }
+
+        self.frame_index = (self.frame_index + 1) % self.channels;
+        Some(S::Item::zero_value())
+    }
+}
+
+impl<S> Source for Deferred<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match &self.source {
+            Some(source) => source.current_frame_len(),
+            None => Some((self.channels - self.frame_index) as usize),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Position {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            frames: Arc::new(AtomicU64::new(0)),
+            sample_rate,
+        }
+    }
+
+    pub fn set(&self, frames: u64) {
+        self.frames.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Duration {
+        let frames = self.frames.load(Ordering::Relaxed);
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    const SAMPLE_RATE: u32 = 100;
+
+    /// A stereo track where each frame's samples are its index (left) and its negated index (right).
+    struct TestTrack(u16);
+
+    impl Track for TestTrack {
+        type Source = SamplesBuffer<i16>;
+
+        fn open(&self) -> Option<Self::Source> {
+            let samples = (0..self.0 as i16).flat_map(|i| [i, -i]).collect::<Vec<_>>();
+            Some(SamplesBuffer::new(2, SAMPLE_RATE, samples))
+        }
+    }
+
+    fn track_source(
+        frames: u16,
+        loop_points: Option<LoopPoints>,
+        start: Duration,
+    ) -> (TrackSource<TestTrack>, Position) {
+        let track = TestTrack(frames);
+        let source = track.open().unwrap();
+        let (mut source, position) = TrackSource::with_track(track, source, loop_points);
+        source.skip_to(start);
+        (source, position)
+    }
+
+    /// The left channel of the next `frames` frames.
+    fn take_frames(source: &mut TrackSource<TestTrack>, frames: usize) -> Vec<i16> {
+        source.by_ref().take(frames * 2).step_by(2).collect()
+    }
+
+    #[test]
+    fn plays_once_without_looping() {
+        let (source, _) = track_source(5, None, Duration::ZERO);
+        assert_eq!(
+            source.collect::<Vec<_>>(),
+            [0, 0, 1, -1, 2, -2, 3, -3, 4, -4]
+        );
+    }
+
+    #[test]
+    fn loops_whole_track() {
+        let (mut source, _) = track_source(3, Some(LoopPoints::WHOLE_TRACK), Duration::ZERO);
+        assert_eq!(take_frames(&mut source, 7), [0, 1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn plays_intro_once_then_loops_region() {
+        let loop_points = LoopPoints {
+            start: 2,
+            length: Some(3),
+        };
+        let (mut source, _) = track_source(8, Some(loop_points), Duration::ZERO);
+        assert_eq!(
+            take_frames(&mut source, 11),
+            [0, 1, 2, 3, 4, 2, 3, 4, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn loop_is_sample_accurate() {
+        let loop_points = LoopPoints {
+            start: 1,
+            length: Some(2),
+        };
+        let (source, _) = track_source(4, Some(loop_points), Duration::ZERO);
+        assert_eq!(
+            source.take(10).collect::<Vec<_>>(),
+            [0, 0, 1, -1, 2, -2, 1, -1, 2, -2]
+        );
+    }
+
+    #[test]
+    fn loops_to_start_at_end_without_length() {
+        let loop_points = LoopPoints {
+            start: 3,
+            length: None,
+        };
+        let (mut source, _) = track_source(5, Some(loop_points), Duration::ZERO);
+        assert_eq!(take_frames(&mut source, 9), [0, 1, 2, 3, 4, 3, 4, 3, 4]);
+    }
+
+    #[test]
+    fn loop_end_past_track_end_wraps_at_track_end() {
+        let loop_points = LoopPoints {
+            start: 1,
+            length: Some(100),
+        };
+        let (mut source, _) = track_source(3, Some(loop_points), Duration::ZERO);
+        assert_eq!(take_frames(&mut source, 7), [0, 1, 2, 1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn starting_past_loop_end_wraps() {
+        let loop_points = LoopPoints {
+            start: 2,
+            length: Some(3),
+        };
+        // 6 frames in is one frame past the loop end
+        let start = Duration::from_secs_f64(6.0 / SAMPLE_RATE as f64);
+        let (mut source, position) = track_source(8, Some(loop_points), start);
+        assert_eq!(
+            position.get(),
+            Duration::from_secs_f64(3.0 / SAMPLE_RATE as f64)
+        );
+        assert_eq!(take_frames(&mut source, 4), [3, 4, 2, 3]);
+    }
+
+    #[test]
+    fn position_follows_loops() {
+        let loop_points = LoopPoints {
+            start: 2,
+            length: Some(3),
+        };
+        let (mut source, position) = track_source(8, Some(loop_points), Duration::ZERO);
+        take_frames(&mut source, 6);
+        assert_eq!(
+            position.get(),
+            Duration::from_secs_f64(3.0 / SAMPLE_RATE as f64)
+        );
+    }
+
+    #[test]
+    fn empty_track_does_not_loop_forever() {
+        let (mut source, _) = track_source(0, Some(LoopPoints::WHOLE_TRACK), Duration::ZERO);
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn deferred_plays_silence_until_ready() {
+        let (ready, wait) = std::sync::mpsc::channel::<()>();
+        let mut source = Deferred::spawn(2, SAMPLE_RATE, move || {
+            wait.recv().unwrap();
+            TestTrack(3).open().unwrap()
+        });
+        assert_eq!(source.by_ref().take(3).collect::<Vec<_>>(), [0, 0, 0]);
+
+        ready.send(()).unwrap();
+        let rest = source.collect::<Vec<_>>();
+        // the rest of the silent frame, then (eventually) the source
+        let silence = rest.iter().take_while(|&&sample| sample == 0).count();
+        assert!((silence + 3) % 2 == 0);
+        assert_eq!(rest[silence - 1..], [0, 1, -1, 2, -2]);
+    }
+}
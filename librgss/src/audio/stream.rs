@@ -0,0 +1,148 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use camino::Utf8PathBuf;
+use std::time::Duration;
+
+use super::{source::Position, PlaybackStatus};
+
+pub(super) struct Stream {
+    pub sink: rodio::Sink,
+    pub path: Utf8PathBuf,
+    position: Position,
+    /// The volume (0-100) set when the stream was last played, before any fade is applied.
+    volume: u32,
+    /// The pitch (as a percentage of normal speed) set when the stream was last played.
+    pitch: u32,
+    /// How far along the current volume envelope is, from 0 (silent) to 1 (full volume).
+    envelope: f32,
+    /// The gain from the player's volume sliders.
+    mix: f32,
+    fade: Option<Fade>,
+}
+
+struct Fade {
+    /// When the fade started, measured by the output's clock.
+    start: Duration,
+    duration: Duration,
+    from: f32,
+    to: f32,
+}
+
+impl Stream {
+    pub fn new(
+        sink: rodio::Sink,
+        path: Utf8PathBuf,
+        position: Position,
+        volume: u32,
+        pitch: u32,
+        mix: f32,
+    ) -> Self {
+        let mut this = Self {
+            sink,
+            path,
+            position,
+            volume,
+            pitch,
+            envelope: 1.0,
+            mix,
+            fade: None,
+        };
+        this.set_volume_pitch(volume, pitch);
+        this
+    }
+
+    /// Sets the volume and pitch of this stream, cancelling any fade in progress.
+    pub fn set_volume_pitch(&mut self, volume: u32, pitch: u32) {
+        self.volume = volume;
+        self.pitch = pitch;
+        self.envelope = 1.0;
+        self.fade = None;
+        self.sink.set_volume(self.gain());
+        self.sink.set_speed(pitch as f32 / 100.);
+    }
+
+    /// Sets the gain from the player's volume sliders, keeping any fade in progress.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix;
+        self.sink.set_volume(self.gain() * self.envelope);
+    }
+
+    fn gain(&self) -> f32 {
+        self.volume as f32 / 100. * self.mix
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        PlaybackStatus {
+            path: self.path.clone(),
+            position: self.position.get(),
+            volume: self.volume,
+            pitch: self.pitch,
+        }
+    }
+
+    /// Fades from the current volume to silence. The stream should be stopped afterwards.
+    pub fn fade_out(&mut self, duration: Duration, now: Duration) {
+        self.start_fade(0.0, duration, now);
+    }
+
+    /// Fades in from silence to the volume of this stream.
+    pub fn fade_in(&mut self, duration: Duration, now: Duration) {
+        self.envelope = 0.0;
+        self.sink.set_volume(0.0);
+        self.start_fade(1.0, duration, now);
+    }
+
+    fn start_fade(&mut self, to: f32, duration: Duration, now: Duration) {
+        self.fade = Some(Fade {
+            start: now,
+            duration,
+            from: self.envelope,
+            to,
+        });
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Applies the volume envelope of the current fade.
+    ///
+    /// Returns true once a fade out has finished, at which point the stream should be stopped.
+    pub fn update_fade(&mut self, now: Duration) -> bool {
+        let Some(fade) = &self.fade else {
+            return false;
+        };
+
+        let progress = if fade.duration.is_zero() {
+            1.0
+        } else {
+            let elapsed = now.saturating_sub(fade.start);
+            (elapsed.as_secs_f32() / fade.duration.as_secs_f32()).min(1.0)
+        };
+        self.envelope = fade.from + (fade.to - fade.from) * progress;
+        self.sink.set_volume(self.gain() * self.envelope);
+
+        if progress < 1.0 {
+            return false;
+        }
+
+        let faded_out = fade.to == 0.0;
+        self.fade = None;
+        faded_out
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{io::Cursor, sync::Arc};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use rustysynth::{MidiFile, MidiFileLoopType, SoundFont};
+
+use super::error::{read_file, read_start, Error, Result};
+use super::se_cache::Sound;
+use crate::FileSystem;
+
+/// A track that has been read and checked on the caller's side, so bad files are reported to the game
+/// instead of failing on the audio thread.
+pub(super) struct Track {
+    pub path: Utf8PathBuf,
+    pub kind: TrackKind,
+}
+
+pub(super) enum TrackKind {
+    /// Anything rodio can decode.
+    Sampled(Arc<[u8]>),
+    /// Decoded ahead of time (used for sound effects, which are short and played often).
+    Decoded(Arc<Sound>),
+    Midi {
+        file: Arc<MidiFile>,
+        soundfont: Arc<SoundFont>,
+    },
+}
+
+impl Track {
+    /// Reads the track at `path`. `soundfont` is only called if the track is a MIDI file.
+    pub fn load(
+        filesystem: &FileSystem,
+        path: Utf8PathBuf,
+        soundfont: impl FnOnce() -> Result<Arc<SoundFont>>,
+    ) -> Result<Self> {
+        Self::read(filesystem, path, soundfont, false)
+    }
+
+    /// Like [`Track::load`], but decodes the whole track up front (unless it's a MIDI file).
+    pub fn load_decoded(
+        filesystem: &FileSystem,
+        path: Utf8PathBuf,
+        soundfont: impl FnOnce() -> Result<Arc<SoundFont>>,
+    ) -> Result<Self> {
+        Self::read(filesystem, path, soundfont, true)
+    }
+
+    fn read(
+        filesystem: &FileSystem,
+        path: Utf8PathBuf,
+        soundfont: impl FnOnce() -> Result<Arc<SoundFont>>,
+        decode: bool,
+    ) -> Result<Self> {
+        let data = read_file(filesystem, &path)?;
+
+        let kind = if is_midi(&data) {
+            let file =
+                MidiFile::new_with_loop_type(&mut data.as_slice(), MidiFileLoopType::RpgMaker)
+                    .map_err(|source| Error::Midi {
+                        path: path.clone(),
+                        source,
+                    })?;
+            TrackKind::Midi {
+                file: Arc::new(file),
+                soundfont: soundfont()?,
+            }
+        } else if decode {
+            let sound = Sound::decode(data).map_err(|source| Error::Decode {
+                path: path.clone(),
+                source,
+            })?;
+            TrackKind::Decoded(Arc::new(sound))
+        } else {
+            let data: Arc<[u8]> = data.into();
+            // decoding the header is enough to catch missing codecs and garbage files
+            rodio::Decoder::new(Cursor::new(data.clone())).map_err(|source| Error::Decode {
+                path: path.clone(),
+                source,
+            })?;
+            TrackKind::Sampled(data)
+        };
+
+        Ok(Self { path, kind })
+    }
+}
+
+/// A track that has been found and sniffed on the caller's side, so missing and obviously broken files are still
+/// reported to the game, while the slow part (reading and decoding the whole file) happens on the audio thread.
+pub(super) struct TrackRequest {
+    pub path: Utf8PathBuf,
+    /// Loaded up front for MIDI files, so a missing SoundFont is reported to the game too.
+    soundfont: Option<Arc<SoundFont>>,
+}
+
+impl TrackRequest {
+    /// Checks that `path` exists and looks like something we can play. `soundfont` is only called for MIDI files.
+    pub fn check(
+        filesystem: &FileSystem,
+        path: Utf8PathBuf,
+        soundfont: impl FnOnce() -> Result<Arc<SoundFont>>,
+    ) -> Result<Self> {
+        let header = read_start(filesystem, &path, HEADER_LEN as u64)?;
+
+        let soundfont = if is_midi(&header) {
+            Some(soundfont()?)
+        } else if is_sampled(&header) {
+            None
+        } else {
+            return Err(Error::Decode {
+                path,
+                source: rodio::decoder::DecoderError::UnrecognizedFormat,
+            });
+        };
+
+        Ok(Self { path, soundfont })
+    }
+
+    /// Reads the whole track.
+    pub fn load(self, filesystem: &FileSystem) -> Result<Track> {
+        let Self { path, soundfont } = self;
+        // if the file has turned into a MIDI file since it was checked, we don't have a SoundFont for it
+        let unrecognized = || Error::Decode {
+            path: path.clone(),
+            source: rodio::decoder::DecoderError::UnrecognizedFormat,
+        };
+        Track::load(filesystem, path.clone(), || {
+            soundfont.ok_or_else(unrecognized)
+        })
+    }
+}
+
+impl std::fmt::Debug for TrackRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TrackRequest").field(&self.path).finish()
+    }
+}
+
+impl std::fmt::Debug for Track {
+    // the data is far too big to print
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Track").field(&self.path).finish()
+    }
+}
+
+/// Enough of a file to tell what format it's in.
+const HEADER_LEN: usize = 12;
+
+fn is_midi(data: &[u8]) -> bool {
+    data.starts_with(b"MThd")
+}
+
+/// Whether `header` looks like a format rodio can decode (WAV, Ogg Vorbis, FLAC or MP3).
+fn is_sampled(header: &[u8]) -> bool {
+    let wav = header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WAVE");
+    // MP3s either start with an ID3 tag, or go straight into a frame (which starts with 11 set bits)
+    let mp3 =
+        header.starts_with(b"ID3") || matches!(header, [0xFF, second, ..] if second & 0xE0 == 0xE0);
+    wav || mp3 || header.starts_with(b"OggS") || header.starts_with(b"fLaC")
+}
+
+pub(super) fn load_soundfont(filesystem: &FileSystem, path: &Utf8Path) -> Result<Arc<SoundFont>> {
+    let data = read_file(filesystem, path)?;
+    let soundfont = SoundFont::new(&mut data.as_slice()).map_err(|source| Error::SoundFont {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(Arc::new(soundfont))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_formats() {
+        assert!(is_sampled(b"RIFF\x24\x08\0\0WAVEfmt "));
+        assert!(is_sampled(b"OggS\0\x02\0\0\0\0\0\0"));
+        assert!(is_sampled(b"ID3\x04\0\0\0\0\0\0\0\0"));
+        assert!(is_sampled(&[0xFF, 0xFB, 0x90, 0x64]));
+        assert!(!is_sampled(b"RIFF\x24\x08\0\0AVI LIST"));
+        assert!(!is_sampled(b"not audio at all"));
+        assert!(!is_sampled(b""));
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of Sapphire.
+//
+// Sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// The least time between saves. Options menus set the volume every frame while a key is held,
+/// and writing the file each time would be wasteful.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The volume sliders players can set, from 0 to 100. These are saved between sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Volumes {
+    pub master: u32,
+    pub bgm: u32,
+    pub bgs: u32,
+    pub me: u32,
+    pub se: u32,
+}
+
+/// What a volume slider controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VolumeCategory {
+    /// Everything.
+    Master,
+    Bgm,
+    Bgs,
+    Me,
+    Se,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Self {
+            master: 100,
+            bgm: 100,
+            bgs: 100,
+            me: 100,
+            se: 100,
+        }
+    }
+}
+
+impl Volumes {
+    pub fn get(&self, category: VolumeCategory) -> u32 {
+        match category {
+            VolumeCategory::Master => self.master,
+            VolumeCategory::Bgm => self.bgm,
+            VolumeCategory::Bgs => self.bgs,
+            VolumeCategory::Me => self.me,
+            VolumeCategory::Se => self.se,
+        }
+    }
+
+    /// Sets the volume of `category`, clamped to 100.
+    pub fn set(&mut self, category: VolumeCategory, volume: u32) {
+        let volume = volume.min(100);
+        match category {
+            VolumeCategory::Master => self.master = volume,
+            VolumeCategory::Bgm => self.bgm = volume,
+            VolumeCategory::Bgs => self.bgs = volume,
+            VolumeCategory::Me => self.me = volume,
+            VolumeCategory::Se => self.se = volume,
+        }
+    }
+
+    /// The gain applied to everything in `category`, including the master volume.
+    pub(super) fn gain(&self, category: VolumeCategory) -> f32 {
+        let master = self.master as f32 / 100.;
+        match category {
+            VolumeCategory::Master => master,
+            _ => master * self.get(category) as f32 / 100.,
+        }
+    }
+
+    /// Reads saved volumes, using the defaults if there aren't any (or they can't be read).
+    pub(super) fn load(path: &Utf8Path) -> Self {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        toml::from_str(&text).unwrap_or_else(|error| {
+            eprintln!("Ignoring invalid volume settings in {path}: {error}");
+            Self::default()
+        })
+    }
+
+    pub(super) fn save(&self, path: &Utf8Path) -> std::io::Result<()> {
+        let text = toml::to_string(self).expect("volumes can always be serialized");
+        std::fs::write(path, text)
+    }
+}
+
+/// Saves the volume sliders when they change, at most once every [`SAVE_INTERVAL`].
+pub(super) struct VolumeSaver {
+    path: Utf8PathBuf,
+    /// Volumes that haven't been saved yet.
+    pending: Option<Volumes>,
+    last_saved: Option<Instant>,
+}
+
+impl VolumeSaver {
+    pub fn new(path: Utf8PathBuf) -> Self {
+        Self {
+            path,
+            pending: None,
+            last_saved: None,
+        }
+    }
+
+    pub fn changed(&mut self, volumes: Volumes) {
+        self.pending = Some(volumes);
+    }
+
+    /// How long until the pending change should be saved, if there is one.
+    pub fn due_in(&self, now: Instant) -> Option<Duration> {
+        self.pending?;
+        let since_save = self.last_saved.map_or(SAVE_INTERVAL, |saved| now - saved);
+        Some(SAVE_INTERVAL.saturating_sub(since_save))
+    }
+
+    /// Saves the pending change, if it's due.
+    pub fn update(&mut self, now: Instant) {
+        if self.due_in(now) == Some(Duration::ZERO) {
+            self.flush(now);
+        }
+    }
+
+    /// Saves the pending change right away. Called when exiting, so the last change isn't lost.
+    pub fn flush(&mut self, now: Instant) {
+        let Some(volumes) = self.pending.take() else {
+            return;
+        };
+        if let Err(error) = volumes.save(&self.path) {
+            eprintln!("Failed to save volume settings to {}: {error}", self.path);
+        }
+        self.last_saved = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gain_includes_master() {
+        let volumes = Volumes {
+            master: 50,
+            se: 50,
+            ..Default::default()
+        };
+        assert_eq!(volumes.gain(VolumeCategory::Master), 0.5);
+        assert_eq!(volumes.gain(VolumeCategory::Se), 0.25);
+        assert_eq!(volumes.gain(VolumeCategory::Bgm), 0.5);
+    }
+
+    #[test]
+    fn partial_settings_use_defaults() {
+        let volumes: Volumes = toml::from_str("bgm = 30").unwrap();
+        assert_eq!(volumes.bgm, 30);
+        assert_eq!(volumes.master, 100);
+    }
+
+    #[test]
+    fn saves_are_throttled() {
+        let path = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sapphire-volume-saver-{}.toml", std::process::id()));
+        let mut saver = VolumeSaver::new(path.clone());
+        let start = Instant::now();
+
+        let mut volumes = Volumes::default();
+        volumes.set(VolumeCategory::Bgm, 90);
+        saver.changed(volumes);
+        assert_eq!(saver.due_in(start), Some(Duration::ZERO));
+        saver.update(start);
+        assert_eq!(Volumes::load(&path).bgm, 90);
+
+        // held slider keys change the volume every frame
+        for frame in 1..10 {
+            let now = start + Duration::from_millis(frame * 16);
+            volumes.set(VolumeCategory::Bgm, 90 - frame as u32);
+            saver.changed(volumes);
+            saver.update(now);
+        }
+        assert_eq!(Volumes::load(&path).bgm, 90);
+        assert!(saver.due_in(start + Duration::from_millis(500)) > Some(Duration::ZERO));
+
+        saver.update(start + SAVE_INTERVAL);
+        assert_eq!(Volumes::load(&path).bgm, 81);
+        assert_eq!(saver.due_in(start + SAVE_INTERVAL), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
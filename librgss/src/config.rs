@@ -0,0 +1,403 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{collections::HashMap, io::Read};
+
+use crate::{FileSystem, GamepadButton, KeyBind};
+
+/// Engine configuration, loaded from `sapphire.toml` in the game directory.
+///
+/// Every field has a default, so a missing file (or a missing key) is not an error.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Ruby scripts evaluated after the RPG module is defined, but before the game's own scripts.
+    ///
+    /// Paths are resolved through the game's filesystem.
+    pub preload_scripts: Vec<Utf8PathBuf>,
+    /// Ruby scripts evaluated after the game's own scripts have finished.
+    ///
+    /// Paths are resolved through the game's filesystem.
+    pub postload_scripts: Vec<Utf8PathBuf>,
+    /// Run without showing any dialogs (for example on CI). Errors are only printed to stderr.
+    pub headless: bool,
+    /// Playtest mode, as launched from the editor with `debug` or `test`. Sets `$DEBUG` and `$TEST`.
+    pub debug: bool,
+    /// Battle test mode, as launched from the editor with `btest`. Sets `$BTEST`.
+    pub battle_test: bool,
+    /// Which version of RGSS the game expects. Detected from the scripts file if not set.
+    pub rgss_version: Option<RgssVersion>,
+    /// Path to the game's scripts. Read from `Game.ini` if not set.
+    ///
+    /// For RGSS1 games this falls back to `Data/xScripts.rxdata` (the name ModShot uses), as it always has.
+    pub scripts_path: Option<Utf8PathBuf>,
+    pub audio: AudioConfig,
+    pub input: InputConfig,
+}
+
+/// The `[input]` table of `sapphire.toml`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    /// How far (from 0 to 1) an analog stick has to be pushed before it counts as a press. Defaults to 0.25.
+    pub gamepad_deadzone: Option<f32>,
+    /// Gamepad buttons for each button, replacing the default gamepad buttons for that button.
+    ///
+    /// For example `action = ["south", "east"]`.
+    pub gamepad_bindings: HashMap<KeyBind, Vec<GamepadButton>>,
+    /// Records every frame of input to this file, so it can be replayed later.
+    pub record: Option<Utf8PathBuf>,
+    /// Plays back a recording instead of reading the keyboard, mouse and gamepads.
+    /// The game exits once the recording runs out.
+    pub replay: Option<Utf8PathBuf>,
+}
+
+/// The `[audio]` table of `sapphire.toml`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// The SoundFont used to play MIDI files. Resolved through the game's filesystem.
+    ///
+    /// If not set, `soundfont.sf2` is used (if it exists).
+    pub soundfont: Option<Utf8PathBuf>,
+    pub backend: AudioBackend,
+    /// The name of the output device to play on. Uses the default device if not set (or if it can't be found).
+    pub device: Option<String>,
+    /// Where the offline backend writes its mixdown. Defaults to `mixdown.wav`.
+    pub mixdown_path: Option<Utf8PathBuf>,
+    /// What happens when the game plays a missing or broken audio file.
+    pub errors: AudioErrorMode,
+    /// How many bytes of decoded sound effects to keep in memory. Defaults to 32 MiB.
+    pub se_cache_size: Option<usize>,
+    /// How many sound effects can play at once. Defaults to 16.
+    pub se_voices: Option<usize>,
+    /// What happens when a sound effect is played while `se_voices` are already playing.
+    pub se_eviction: SeEviction,
+    /// Where the player's volume settings are saved. Defaults to `volume.toml`.
+    pub volume_path: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeEviction {
+    /// Stop the sound effect that started first.
+    #[default]
+    Oldest,
+    /// Stop the quietest sound effect, if it isn't louder than the new one.
+    Quietest,
+    /// Don't play the new sound effect.
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioErrorMode {
+    /// Raise an exception in the game, like RGSS does.
+    #[default]
+    Raise,
+    /// Print a warning and carry on.
+    Warn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+    /// The platform's default output device, or `Null` if there isn't one.
+    #[default]
+    Auto,
+    /// Play without an output device. Playback still advances in real time, it just isn't heard.
+    Null,
+    /// Mix everything into a WAV file. Playback only advances when the game renders a frame,
+    /// so the same inputs always produce the same file.
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RgssVersion {
+    /// RPG Maker XP
+    #[default]
+    Rgss1 = 1,
+    /// RPG Maker VX
+    Rgss2 = 2,
+    /// RPG Maker VX Ace
+    Rgss3 = 3,
+}
+
+impl RgssVersion {
+    /// Guesses the version from the extension of a data file (`.rxdata`, `.rvdata`, or `.rvdata2`).
+    pub fn from_data_path(path: impl AsRef<Utf8Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rxdata" => Some(Self::Rgss1),
+            "rvdata" => Some(Self::Rgss2),
+            "rvdata2" => Some(Self::Rgss3),
+            _ => None,
+        }
+    }
+
+    /// The extension used by data files for this version.
+    pub fn data_extension(self) -> &'static str {
+        match self {
+            Self::Rgss1 => "rxdata",
+            Self::Rgss2 => "rvdata",
+            Self::Rgss3 => "rvdata2",
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Utf8Path>) -> color_eyre::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let config = toml::from_str(&text)?;
+        Ok(config)
+    }
+
+    /// Fills in the scripts path and RGSS version (if they weren't set explicitly) by inspecting the game's files.
+    ///
+    /// The scripts path is read from `Game.ini`, falling back to `Data/xScripts.rxdata` or whichever
+    /// `Data/Scripts.*` file exists.
+    pub fn detect_game(&mut self, filesystem: &FileSystem) {
+        if self.scripts_path.is_none() {
+            self.scripts_path = read_ini_scripts_path(filesystem).or_else(|| {
+                let scripts = [RgssVersion::Rgss1, RgssVersion::Rgss2, RgssVersion::Rgss3]
+                    .into_iter()
+                    .map(|version| {
+                        Utf8PathBuf::from(format!("Data/Scripts.{}", version.data_extension()))
+                    });
+                // lookups ignore extensions, so use the path that was found to tell the versions apart
+                std::iter::once(Utf8PathBuf::from(MODSHOT_SCRIPTS_PATH))
+                    .chain(scripts)
+                    .find_map(|path| filesystem.desensitize(path).map(Utf8Path::to_path_buf))
+            });
+        }
+
+        if self.rgss_version.is_none() {
+            self.rgss_version = self
+                .scripts_path
+                .as_ref()
+                .and_then(RgssVersion::from_data_path);
+        }
+    }
+
+    pub fn rgss_version(&self) -> RgssVersion {
+        self.rgss_version.unwrap_or_default()
+    }
+
+    pub fn scripts_path(&self) -> Utf8PathBuf {
+        self.scripts_path
+            .clone()
+            .unwrap_or_else(|| match self.rgss_version() {
+                RgssVersion::Rgss1 => Utf8PathBuf::from(MODSHOT_SCRIPTS_PATH),
+                version => Utf8PathBuf::from(format!("Data/Scripts.{}", version.data_extension())),
+            })
+    }
+
+    /// Applies the arguments RPG Maker passes to the player when testing from the editor.
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = impl AsRef<str>>) {
+        for arg in args {
+            match arg.as_ref() {
+                "debug" | "test" => self.debug = true,
+                "btest" => self.battle_test = true,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Where ModShot (and so OneShot and its mods) keep their scripts.
+const MODSHOT_SCRIPTS_PATH: &str = "Data/xScripts.rxdata";
+
+/// Reads the `Scripts` key from `Game.ini`, if it exists.
+fn read_ini_scripts_path(filesystem: &FileSystem) -> Option<Utf8PathBuf> {
+    let mut file = filesystem.read_file("Game.ini").ok()?;
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).ok()?;
+
+    // Game.ini is often not UTF-8, but the keys we care about are always ASCII
+    let text = String::from_utf8_lossy(&bytes);
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("scripts") {
+            return None;
+        }
+        // paths in Game.ini use windows path separators
+        Some(Utf8PathBuf::from(value.trim().replace('\\', "/")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `files` to a fresh game directory.
+    fn game_filesystem(test: &str, files: &[(&str, &str)]) -> FileSystem {
+        let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sapphire-config-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Data")).unwrap();
+        for (path, contents) in files {
+            std::fs::write(dir.join(path), contents).unwrap();
+        }
+        FileSystem::new(dir, None).unwrap()
+    }
+
+    #[test]
+    fn reads_the_scripts_path_from_game_ini() {
+        let cases = [
+            (
+                "plain",
+                "[Game]\r\nScripts=Data\\Scripts.rvdata2\r\n",
+                Some("Data/Scripts.rvdata2"),
+            ),
+            (
+                "case",
+                "[Game]\nscripts = Data\\Scripts.rxdata \n",
+                Some("Data/Scripts.rxdata"),
+            ),
+            (
+                "other-keys",
+                "[Game]\nLibrary=RGSS104E.dll\nTitle=Game\n",
+                None,
+            ),
+            (
+                "latin1",
+                "[Game]\nTitle=Caf\u{e9}\nScripts=Data\\Scripts.rvdata\n",
+                Some("Data/Scripts.rvdata"),
+            ),
+        ];
+
+        for (name, ini, expected) in cases {
+            let filesystem = game_filesystem(&format!("ini-{name}"), &[("Game.ini", ini)]);
+            assert_eq!(
+                read_ini_scripts_path(&filesystem),
+                expected.map(Utf8PathBuf::from),
+                "{name}"
+            );
+        }
+
+        let filesystem = game_filesystem("ini-missing", &[]);
+        assert_eq!(read_ini_scripts_path(&filesystem), None);
+    }
+
+    #[test]
+    fn detects_the_scripts_path_and_version() {
+        // name, files, scripts path, version
+        type Case<'a> = (
+            &'a str,
+            &'a [(&'a str, &'a str)],
+            Option<&'a str>,
+            Option<RgssVersion>,
+        );
+        let cases: [Case; 6] = [
+            (
+                "ini",
+                &[
+                    ("Game.ini", "Scripts=Data\\Scripts.rvdata2"),
+                    ("Data/Scripts.rvdata2", ""),
+                ],
+                Some("Data/Scripts.rvdata2"),
+                Some(RgssVersion::Rgss3),
+            ),
+            (
+                "modshot",
+                &[("Data/xScripts.rxdata", ""), ("Data/Scripts.rxdata", "")],
+                Some("Data/xScripts.rxdata"),
+                Some(RgssVersion::Rgss1),
+            ),
+            (
+                "xp",
+                &[("Data/Scripts.rxdata", "")],
+                Some("Data/Scripts.rxdata"),
+                Some(RgssVersion::Rgss1),
+            ),
+            (
+                "vx",
+                &[("Data/Scripts.rvdata", "")],
+                Some("Data/Scripts.rvdata"),
+                Some(RgssVersion::Rgss2),
+            ),
+            (
+                "vxace",
+                &[("Data/Scripts.rvdata2", "")],
+                Some("Data/Scripts.rvdata2"),
+                Some(RgssVersion::Rgss3),
+            ),
+            ("empty", &[], None, None),
+        ];
+
+        for (name, files, scripts_path, rgss_version) in cases {
+            let filesystem = game_filesystem(&format!("detect-{name}"), files);
+            let mut config = Config::default();
+            config.detect_game(&filesystem);
+            assert_eq!(
+                config.scripts_path,
+                scripts_path.map(Utf8PathBuf::from),
+                "{name}"
+            );
+            assert_eq!(config.rgss_version, rgss_version, "{name}");
+        }
+    }
+
+    #[test]
+    fn detection_keeps_explicit_settings() {
+        let filesystem = game_filesystem("explicit", &[("Data/Scripts.rvdata2", "")]);
+        let mut config = Config {
+            rgss_version: Some(RgssVersion::Rgss2),
+            ..Default::default()
+        };
+        config.detect_game(&filesystem);
+        assert_eq!(
+            config.scripts_path.as_deref(),
+            Some(Utf8Path::new("Data/Scripts.rvdata2"))
+        );
+        assert_eq!(config.rgss_version(), RgssVersion::Rgss2);
+
+        let mut config = Config {
+            scripts_path: Some(Utf8PathBuf::from("Data/Custom.rvdata")),
+            ..Default::default()
+        };
+        config.detect_game(&filesystem);
+        assert_eq!(config.scripts_path(), "Data/Custom.rvdata");
+        assert_eq!(config.rgss_version(), RgssVersion::Rgss2);
+    }
+
+    #[test]
+    fn scripts_path_defaults() {
+        let cases = [
+            (None, "Data/xScripts.rxdata"),
+            (Some(RgssVersion::Rgss1), "Data/xScripts.rxdata"),
+            (Some(RgssVersion::Rgss2), "Data/Scripts.rvdata"),
+            (Some(RgssVersion::Rgss3), "Data/Scripts.rvdata2"),
+        ];
+
+        for (rgss_version, expected) in cases {
+            let config = Config {
+                rgss_version,
+                ..Default::default()
+            };
+            assert_eq!(config.scripts_path(), expected, "{rgss_version:?}");
+        }
+    }
+}
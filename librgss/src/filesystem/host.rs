@@ -56,14 +56,14 @@ impl FileSystemTrait for FileSystem {
         path.read_dir_utf8()?
             .map_ok(|entry| {
                 let path = entry.path();
+                let metadata = std::fs::metadata(path)?;
+
                 // FIXME windows path shenanigans
                 let path = path
                     .strip_prefix(&self.root_path)
                     .unwrap_or(path)
                     .to_path_buf();
 
-                let metadata = std::fs::metadata(&path)?;
-
                 Ok(Entry {
                     path,
                     is_file: metadata.is_file(),
@@ -84,4 +84,9 @@ impl FileSystem {
     pub fn read_file(&self, path: impl AsRef<Utf8Path>) -> Result<Box<dyn File>> {
         self.fs.read_file(path.as_ref())
     }
+
+    /// The path a file is actually stored at. Lookups ignore case and extensions, like RGSS does.
+    pub fn desensitize(&self, path: impl AsRef<Utf8Path>) -> Option<&Utf8Path> {
+        self.fs.desensitize(path)
+    }
 }
@@ -33,11 +33,11 @@ pub struct Font {
     pub italic: bool,
     pub color: Color,
 
-    #[cfg(feature = "rgss2")]
+    /// Only used by RGSS2 and up.
     pub shadow: bool,
-    #[cfg(feature = "rgss3")]
+    /// Only used by RGSS3.
     pub outline: Color,
-    #[cfg(feature = "rgss3")]
+    /// Only used by RGSS3.
     pub out_color: Color,
 }
 
@@ -55,11 +55,9 @@ impl Fonts {
             bold: false,
             italic: false,
             color: Color::WHITE,
-            #[cfg(feature = "rgss2")] // FIXME not 100% accurate
+            // FIXME not 100% accurate
             shadow: false,
-            #[cfg(feature = "rgss3")]
             outline: Color::WHITE,
-            #[cfg(feature = "rgss3")]
             out_color: Color::GREY,
         };
 
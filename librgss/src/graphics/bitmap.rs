@@ -49,14 +49,8 @@ impl Bitmap {
             .graphics_state
             .device
             .create_texture(&bitmap_texture_descriptor(width, height));
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let internal = BitmapInternal {
-            texture,
-            view,
-            font: Font::default(fonts),
-        };
-        let key = arenas.bitmap.insert(internal);
+        let key = arenas.bitmap.insert(BitmapInternal::new(texture, fonts));
 
         Self { key }
     }
@@ -81,30 +75,25 @@ impl Bitmap {
             wgpu::util::TextureDataOrder::LayerMajor,
             &image,
         );
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let internal = BitmapInternal {
-            texture,
-            view,
-            font: Font::default(fonts),
-        };
-        let key = arenas.bitmap.insert(internal);
+        let key = arenas.bitmap.insert(BitmapInternal::new(texture, fonts));
 
         Self { key }
     }
 
-    pub fn width(&self, arenas: &Arenas) -> u32 {
-        let internal = arenas.bitmap.get(self.key).unwrap();
-        internal.texture.width()
+    /// `None` if the bitmap has been disposed, as with the other methods here.
+    pub fn width(&self, arenas: &Arenas) -> Option<u32> {
+        let internal = arenas.bitmap.get(self.key)?;
+        Some(internal.texture.width())
     }
 
-    pub fn height(&self, arenas: &Arenas) -> u32 {
-        let internal = arenas.bitmap.get(self.key).unwrap();
-        internal.texture.height()
+    pub fn height(&self, arenas: &Arenas) -> Option<u32> {
+        let internal = arenas.bitmap.get(self.key)?;
+        Some(internal.texture.height())
     }
 
-    pub fn text_size(&self, arenas: &Arenas, fonts: &mut Fonts, text: &str) -> Rect {
-        let BitmapInternal { font, .. } = arenas.bitmap.get(self.key).unwrap();
+    pub fn text_size(&self, arenas: &Arenas, fonts: &mut Fonts, text: &str) -> Option<Rect> {
+        let BitmapInternal { font, .. } = arenas.bitmap.get(self.key)?;
         let Fonts { font_system, .. } = fonts;
         println!("{text}");
 
@@ -125,7 +114,7 @@ impl Bitmap {
             println!("hsdfvmhfsdmghfdhgcsfhgncsdfh {} {}", run.line_w, run.line_y)
         }
 
-        Rect::new(0, 0, width as u32, height as u32)
+        Some(Rect::new(0, 0, width as u32, height as u32))
     }
 
     pub fn null() -> Self {
@@ -134,16 +123,29 @@ impl Bitmap {
         }
     }
 
-    pub fn font<'a>(&self, arenas: &'a Arenas) -> &'a Font {
-        // FIXME
-        let internal = arenas.bitmap.get(self.key).unwrap();
-        &internal.font
+    pub fn disposed(&self, arenas: &Arenas) -> bool {
+        !arenas.bitmap.contains_key(self.key)
+    }
+
+    pub fn font<'a>(&self, arenas: &'a Arenas) -> Option<&'a Font> {
+        let internal = arenas.bitmap.get(self.key)?;
+        Some(&internal.font)
     }
 
-    pub fn font_mut<'a>(&self, arenas: &'a mut Arenas) -> &'a mut Font {
-        // FIXME
-        let internal = arenas.bitmap.get_mut(self.key).unwrap();
-        &mut internal.font
+    pub fn font_mut<'a>(&self, arenas: &'a mut Arenas) -> Option<&'a mut Font> {
+        let internal = arenas.bitmap.get_mut(self.key)?;
+        Some(&mut internal.font)
+    }
+}
+
+impl BitmapInternal {
+    fn new(texture: wgpu::Texture, fonts: &Fonts) -> Self {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            font: Font::default(fonts),
+        }
     }
 }
 
@@ -169,3 +171,45 @@ pub(crate) fn bitmap_texture_descriptor(
         view_formats: &[],
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::ViewportInternal;
+
+    /// A device to make textures with, or `None` if there's no adapter (not even a software one).
+    fn test_device() -> Option<wgpu::Device> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+        let (device, _) =
+            pollster::block_on(adapter.request_device(&Default::default(), None)).ok()?;
+        Some(device)
+    }
+
+    #[test]
+    fn bitmaps_kept_across_a_reset_are_disposed() {
+        let Some(device) = test_device() else {
+            eprintln!("no graphics adapter, skipping");
+            return;
+        };
+        let fonts = Fonts::new();
+        let mut arenas = Arenas::default();
+        let global_viewport = arenas.viewport.insert(ViewportInternal::global());
+
+        let texture = device.create_texture(&bitmap_texture_descriptor(32, 16));
+        let bitmap = Bitmap {
+            key: arenas.bitmap.insert(BitmapInternal::new(texture, &fonts)),
+        };
+        assert_eq!(bitmap.width(&arenas), Some(32));
+        assert_eq!(bitmap.height(&arenas), Some(16));
+
+        // the scripts can still hold on to the bitmap, but everything behind it is gone
+        arenas.dispose_all(global_viewport);
+        assert!(bitmap.disposed(&arenas));
+        assert_eq!(bitmap.width(&arenas), None);
+        assert_eq!(bitmap.height(&arenas), None);
+        assert!(bitmap.font(&arenas).is_none());
+        assert!(bitmap.font_mut(&mut arenas).is_none());
+        assert!(arenas.viewport.contains_key(global_viewport));
+    }
+}
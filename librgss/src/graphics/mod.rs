@@ -52,7 +52,8 @@ pub(crate) use window::WindowKey;
 pub use window::{Window, WindowData};
 
 mod z;
-use z::{ZList, Z};
+pub(crate) use z::ZList;
+use z::Z;
 
 mod render;
 
@@ -88,6 +89,8 @@ pub(crate) struct RenderState<'a, 'rpass> {
     render_pass: &'a mut wgpu::RenderPass<'rpass>,
 }
 
+const DEFAULT_FRAMERATE: u16 = 40;
+
 const BITMAP_OPS_DESCRIPTOR: wgpu::CommandEncoderDescriptor<'static> =
     wgpu::CommandEncoderDescriptor {
         label: Some("bitmap operations (this frame)"),
@@ -123,7 +126,7 @@ impl Graphics {
             window,
             filesystem,
             last_render: Instant::now(),
-            framerate: 40,
+            framerate: DEFAULT_FRAMERATE,
             frame_count: 0,
 
             bind_groups,
@@ -212,6 +215,25 @@ impl Graphics {
         self.last_render = Instant::now();
     }
 
+    /// The size of the game's screen, in pixels. The whole surface is the screen, so this is also the surface's size.
+    pub fn screen_size(&self) -> (u32, u32) {
+        let config = &self.graphics_state.surface_config;
+        (config.width, config.height)
+    }
+
+    /// Disposes every object in the arenas apart from the global viewport, and restores the default framerate.
+    ///
+    /// Used when soft resetting, so the scripts start from a clean slate.
+    pub fn reset(&mut self, arenas: &mut Arenas) {
+        arenas.dispose_all(self.global_viewport.key);
+        self.framerate = DEFAULT_FRAMERATE;
+    }
+
+    /// Lets IMEs send composed text to the window. Should only be allowed while scripts want text input.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
     #[cfg(feature = "modshot")]
     pub fn set_window_title(&self, title: &str) {
         self.window.set_title(title)
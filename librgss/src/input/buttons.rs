@@ -24,18 +24,73 @@ pub struct Buttons {
 
     pub(crate) current_states: States,
     pub(crate) last_states: States,
-    pub(crate) repeats: States,
+    /// How many frames each pressed button has been held for, counting the frame it was pressed on.
+    hold_frames: HashMap<Button, u32>,
+    /// Directions that are held, from least to most recently pressed.
+    directions: Vec<KeyBind>,
+    /// Physical inputs that are held down, so a binding stays pressed until all of its inputs are released.
+    held: HashSet<PhysicalInput>,
+    /// Buttons held by [`Buttons::process_simulated`] rather than a physical input.
+    simulated: HashSet<Button>,
+    /// Keys held by simulated named buttons, so they drive their bindings (like Shift driving Run) as the real keys would.
+    simulated_keys: HashSet<KeyCode>,
 }
 
 pub(crate) type States = HashSet<Button>;
 
+/// Which physical inputs each [`KeyBind`] is bound to.
 #[derive(Debug)]
 pub struct Bindings {
-    pub map: enum_map::EnumMap<KeyBind, KeyCode>,
+    map: enum_map::EnumMap<KeyBind, Vec<PhysicalInput>>,
+    /// The reverse of `map`, so key events don't have to search every binding.
+    reverse: HashMap<PhysicalInput, Vec<KeyBind>>,
+}
+
+/// A key or gamepad button that can be bound to a [`KeyBind`].
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum PhysicalInput {
+    Key(KeyCode),
+    Gamepad(GamepadButton),
+}
+
+/// Gamepad buttons, named by their position on the pad rather than their label.
+///
+/// Analog sticks act as four buttons each, which are pressed when the stick is pushed past the deadzone.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// Pressing in the left stick.
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+    RightStickUp,
+    RightStickDown,
+    RightStickLeft,
+    RightStickRight,
 }
 
 // Rebindable keys
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, enum_map::Enum)]
+#[derive(
+    Clone, Copy, Hash, PartialEq, Eq, Debug, enum_map::Enum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum KeyBind {
     Down = 2,
     Left = 4,
@@ -56,8 +111,12 @@ pub enum KeyBind {
     Pause = 42,
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub enum NamedButton {
+    Shift = 21,
+    Ctrl = 22,
+    Alt = 23,
+
     F5 = 25,
     F6 = 26,
     F7 = 27,
@@ -70,7 +129,7 @@ pub enum NamedButton {
 }
 
 // Set of all recognized "Buttons"
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Button {
     KeyBind(KeyBind),
     KeyCode(KeyCode),
@@ -88,32 +147,181 @@ const OTHER_DIRS: [[KeyBind; 3]; 4] = [
     [KeyBind::Left, KeyBind::Right, KeyBind::Up],
     [KeyBind::Down, KeyBind::Up, KeyBind::Right],
     [KeyBind::Down, KeyBind::Up, KeyBind::Left],
-    [KeyBind::Left, KeyBind::Right, KeyBind::Up],
+    [KeyBind::Left, KeyBind::Right, KeyBind::Down],
 ];
+const DIRS: [KeyBind; 4] = [KeyBind::Down, KeyBind::Left, KeyBind::Right, KeyBind::Up];
+/// The diagonal two directions combine into (in numpad notation), or 0 if they're opposites.
+const DIR_COMBOS: [[u8; 4]; 4] = [[2, 1, 3, 0], [1, 4, 0, 7], [3, 0, 6, 9], [0, 7, 9, 8]];
+
+/// How many frames a button has to be held before it starts repeating.
+const REPEAT_DELAY: u32 = 15;
+/// How many frames there are between repeats after that.
+const REPEAT_INTERVAL: u32 = 4;
+
+fn dir_index(dir: KeyBind) -> usize {
+    dir as usize / 2 - 1
+}
+
+impl NamedButton {
+    /// The named button `key` presses, if any. These are pressed even if the key is bound to something else.
+    fn from_key(key: KeyCode) -> Option<Self> {
+        Some(match key {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => Self::Shift,
+            KeyCode::ControlLeft | KeyCode::ControlRight => Self::Ctrl,
+            KeyCode::AltLeft | KeyCode::AltRight => Self::Alt,
+            KeyCode::F5 => Self::F5,
+            KeyCode::F6 => Self::F6,
+            KeyCode::F7 => Self::F7,
+            KeyCode::F8 => Self::F8,
+            KeyCode::F9 => Self::F9,
+            _ => return None,
+        })
+    }
+
+    fn keys(self) -> &'static [KeyCode] {
+        match self {
+            Self::Shift => &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            Self::Ctrl => &[KeyCode::ControlLeft, KeyCode::ControlRight],
+            Self::Alt => &[KeyCode::AltLeft, KeyCode::AltRight],
+            Self::F5 => &[KeyCode::F5],
+            Self::F6 => &[KeyCode::F6],
+            Self::F7 => &[KeyCode::F7],
+            Self::F8 => &[KeyCode::F8],
+            Self::F9 => &[KeyCode::F9],
+            Self::MouseLeft | Self::MouseMiddle | Self::MouseRight => &[],
+        }
+    }
+}
 
 impl Default for Bindings {
     // TODO load from persistent config
     fn default() -> Self {
+        use GamepadButton as Pad;
+        use PhysicalInput::{Gamepad, Key};
+
         let map = enum_map::enum_map! {
-            KeyBind::Down => KeyCode::ArrowDown,
-            KeyBind::Left => KeyCode::ArrowUp,
-            KeyBind::Right => KeyCode::ArrowRight,
-            KeyBind::Up => KeyCode::ArrowUp,
-
-            KeyBind::Action =>KeyCode::KeyZ,
-            KeyBind::Cancel => KeyCode::KeyX,
-            KeyBind::Menu =>KeyCode::KeyA,
-            KeyBind::Items => KeyCode::KeyS,
-            KeyBind::Run => KeyCode::KeyR,
-            KeyBind::Deactivate => KeyCode::KeyC,
-
-            KeyBind::L => KeyCode::KeyQ,
-            KeyBind::R => KeyCode::KeyW,
-
-            KeyBind::Settings => KeyCode::Tab,
-            KeyBind::Pause => KeyCode::KeyP
+            KeyBind::Down => vec![
+                Key(KeyCode::ArrowDown),
+                Gamepad(Pad::DPadDown),
+                Gamepad(Pad::LeftStickDown),
+            ],
+            KeyBind::Left => vec![
+                Key(KeyCode::ArrowLeft),
+                Gamepad(Pad::DPadLeft),
+                Gamepad(Pad::LeftStickLeft),
+            ],
+            KeyBind::Right => vec![
+                Key(KeyCode::ArrowRight),
+                Gamepad(Pad::DPadRight),
+                Gamepad(Pad::LeftStickRight),
+            ],
+            KeyBind::Up => vec![
+                Key(KeyCode::ArrowUp),
+                Gamepad(Pad::DPadUp),
+                Gamepad(Pad::LeftStickUp),
+            ],
+
+            KeyBind::Action => vec![
+                Key(KeyCode::KeyZ),
+                Key(KeyCode::Space),
+                Key(KeyCode::Enter),
+                Gamepad(Pad::South),
+            ],
+            KeyBind::Cancel => vec![
+                Key(KeyCode::KeyX),
+                Key(KeyCode::Escape),
+                Key(KeyCode::Numpad0),
+                Gamepad(Pad::East),
+            ],
+            KeyBind::Menu => vec![Key(KeyCode::KeyA), Gamepad(Pad::North)],
+            KeyBind::Items => vec![Key(KeyCode::KeyS), Gamepad(Pad::West)],
+            KeyBind::Run => vec![
+                Key(KeyCode::KeyR),
+                Key(KeyCode::ShiftLeft),
+                Key(KeyCode::ShiftRight),
+                Gamepad(Pad::LeftTrigger),
+            ],
+            KeyBind::Deactivate => vec![Key(KeyCode::KeyC), Gamepad(Pad::RightTrigger)],
+
+            KeyBind::L => vec![
+                Key(KeyCode::KeyQ),
+                Key(KeyCode::PageUp),
+                Gamepad(Pad::LeftShoulder),
+            ],
+            KeyBind::R => vec![
+                Key(KeyCode::KeyW),
+                Key(KeyCode::PageDown),
+                Gamepad(Pad::RightShoulder),
+            ],
+
+            KeyBind::Settings => vec![Key(KeyCode::Tab), Gamepad(Pad::Select)],
+            KeyBind::Pause => vec![Key(KeyCode::KeyP), Gamepad(Pad::Start)],
         };
-        Self { map }
+        Self::new(map)
+    }
+}
+
+impl Bindings {
+    pub fn new(map: enum_map::EnumMap<KeyBind, Vec<PhysicalInput>>) -> Self {
+        let mut bindings = Self {
+            map,
+            reverse: HashMap::new(),
+        };
+        bindings.rebuild_reverse();
+        bindings
+    }
+
+    fn rebuild_reverse(&mut self) {
+        self.reverse.clear();
+        for (keybind, inputs) in self.map.iter() {
+            for &input in inputs {
+                self.reverse.entry(input).or_default().push(keybind);
+            }
+        }
+    }
+
+    /// The physical inputs bound to `keybind`.
+    pub fn inputs(&self, keybind: KeyBind) -> &[PhysicalInput] {
+        &self.map[keybind]
+    }
+
+    /// The bindings that `input` is bound to.
+    pub fn keybinds(&self, input: PhysicalInput) -> &[KeyBind] {
+        self.reverse.get(&input).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `input` to the inputs bound to `keybind`.
+    pub fn bind(&mut self, keybind: KeyBind, input: PhysicalInput) {
+        if !self.map[keybind].contains(&input) {
+            self.map[keybind].push(input);
+            self.reverse.entry(input).or_default().push(keybind);
+        }
+    }
+
+    /// Replaces the gamepad buttons bound to `keybind`, keeping its keys.
+    pub fn set_gamepad_buttons(&mut self, keybind: KeyBind, buttons: &[GamepadButton]) {
+        let old: Vec<_> = self.map[keybind]
+            .iter()
+            .copied()
+            .filter(|input| matches!(input, PhysicalInput::Gamepad(_)))
+            .collect();
+        for input in old {
+            self.unbind(keybind, input);
+        }
+        for &button in buttons {
+            self.bind(keybind, PhysicalInput::Gamepad(button));
+        }
+    }
+
+    /// Removes `input` from the inputs bound to `keybind`.
+    pub fn unbind(&mut self, keybind: KeyBind, input: PhysicalInput) {
+        self.map[keybind].retain(|&i| i != input);
+        if let Some(keybinds) = self.reverse.get_mut(&input) {
+            keybinds.retain(|&k| k != keybind);
+            if keybinds.is_empty() {
+                self.reverse.remove(&input);
+            }
+        }
     }
 }
 
@@ -123,13 +331,16 @@ impl Default for Buttons {
 
         let current_states = States::default();
         let last_states = States::default();
-        let repeats = States::default();
 
         Self {
             bindings,
             current_states,
             last_states,
-            repeats,
+            hold_frames: HashMap::new(),
+            directions: Vec::with_capacity(4),
+            held: HashSet::new(),
+            simulated: HashSet::new(),
+            simulated_keys: HashSet::new(),
         }
     }
 }
@@ -138,7 +349,46 @@ impl Buttons {
     pub fn start_frame(&mut self) {
         std::mem::swap(&mut self.current_states, &mut self.last_states);
         self.current_states.clone_from(&self.last_states);
-        self.repeats.clear();
+    }
+
+    /// Updates state that depends on every event in the frame, like which direction was pressed last.
+    pub fn end_frame(&mut self) {
+        self.hold_frames
+            .retain(|button, _| self.current_states.contains(button));
+        for &button in &self.current_states {
+            *self.hold_frames.entry(button).or_default() += 1;
+        }
+
+        let states = &self.current_states;
+        self.directions
+            .retain(|&dir| states.contains(&Button::KeyBind(dir)));
+        for dir in DIRS {
+            if self.triggered(Button::KeyBind(dir)) && !self.directions.contains(&dir) {
+                self.directions.push(dir);
+            }
+        }
+    }
+
+    /// Every button held this frame, in no particular order.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = Button> + '_ {
+        self.current_states.iter().copied()
+    }
+
+    /// Replaces this frame's state with a recorded one, ignoring anything physically held.
+    pub fn replay(&mut self, pressed: &[Button]) {
+        self.held.clear();
+        self.current_states.clear();
+        self.current_states.extend(pressed.iter().copied());
+    }
+
+    pub fn clear(&mut self) {
+        self.current_states.clear();
+        self.last_states.clear();
+        self.hold_frames.clear();
+        self.directions.clear();
+        self.held.clear();
+        self.simulated.clear();
+        self.simulated_keys.clear();
     }
 
     pub fn process_key(&mut self, event: winit::event::KeyEvent) {
@@ -147,33 +397,109 @@ impl Buttons {
             return;
         };
 
-        let button = self
-            .bindings
-            .map
-            .iter()
-            .find(|(_, &k)| k == key)
-            .map(|(k, _)| Button::KeyBind(k))
-            .unwrap_or_else(|| match key {
-                KeyCode::F5 => Button::Named(NamedButton::F5),
-                KeyCode::F6 => Button::Named(NamedButton::F6),
-                KeyCode::F7 => Button::Named(NamedButton::F7),
-                KeyCode::F8 => Button::Named(NamedButton::F8),
-                KeyCode::F9 => Button::Named(NamedButton::F9),
-                _ => Button::KeyCode(key),
-            });
+        // key repeat from the OS is ignored, repeat? is timed in frames instead
+        if !event.repeat {
+            self.process_input(PhysicalInput::Key(key), event.state.is_pressed());
+        }
+    }
+
+    /// Updates the state of everything `input` is bound to.
+    pub fn process_input(&mut self, input: PhysicalInput, pressed: bool) {
+        if pressed {
+            self.held.insert(input);
+        } else {
+            self.held.remove(&input);
+        }
+        self.update_input(input);
+    }
+
+    /// Updates everything `input` is bound to after it was pressed or released.
+    fn update_input(&mut self, input: PhysicalInput) {
+        let keybinds = self.bindings.keybinds(input);
+        let named = match input {
+            PhysicalInput::Key(key) => NamedButton::from_key(key),
+            PhysicalInput::Gamepad(_) => None,
+        };
+
+        if let Some(named) = named {
+            let button = Button::Named(named);
+            let held = self.is_held(button);
+            set_button(&mut self.current_states, button, held);
+        } else if let (PhysicalInput::Key(key), []) = (input, keybinds) {
+            let button = Button::KeyCode(key);
+            let held = self.is_held(button);
+            set_button(&mut self.current_states, button, held);
+        }
+
+        for &keybind in keybinds {
+            let button = Button::KeyBind(keybind);
+            let held = self.is_held(button);
+            set_button(&mut self.current_states, button, held);
+        }
+    }
+
+    /// Presses or releases `button` as if a physical input bound to it had been.
+    ///
+    /// Named buttons press their key (e.g. the left Shift for Shift), so anything bound to it is pressed too.
+    pub fn process_simulated(&mut self, button: Button, pressed: bool) {
+        let key = match button {
+            Button::Named(named) => named.keys().first().copied(),
+            _ => None,
+        };
+        if let Some(key) = key {
+            if pressed {
+                self.simulated_keys.insert(key);
+            } else {
+                self.simulated_keys.remove(&key);
+            }
+            self.update_input(PhysicalInput::Key(key));
+            return;
+        }
 
-        if event.repeat {
-            self.repeats.insert(button);
+        if pressed {
+            self.simulated.insert(button);
+        } else {
+            self.simulated.remove(&button);
         }
+        let held = self.is_held(button);
+        set_button(&mut self.current_states, button, held);
+    }
 
-        if event.state.is_pressed() {
-            self.current_states.insert(button);
-        } else if !event.repeat {
-            self.current_states.remove(&button);
+    /// Whether anything is holding `button` down, as several inputs can be bound to the same button.
+    fn is_held(&self, button: Button) -> bool {
+        let physical = match button {
+            Button::KeyBind(keybind) => self
+                .bindings
+                .inputs(keybind)
+                .iter()
+                .any(|&input| self.input_held(input)),
+            Button::Named(named) => named
+                .keys()
+                .iter()
+                .any(|&key| self.input_held(PhysicalInput::Key(key))),
+            Button::KeyCode(key) => self.input_held(PhysicalInput::Key(key)),
+        };
+        physical || self.simulated.contains(&button)
+    }
+
+    fn input_held(&self, input: PhysicalInput) -> bool {
+        match input {
+            PhysicalInput::Key(key) if self.simulated_keys.contains(&key) => true,
+            _ => self.held.contains(&input),
         }
     }
 
-    pub fn process_mouse(&mut self, event: winit::event::MouseButton) {}
+    pub fn process_mouse(&mut self, button: winit::event::MouseButton, pressed: bool) {
+        use winit::event::MouseButton;
+
+        let button = match button {
+            MouseButton::Left => NamedButton::MouseLeft,
+            MouseButton::Middle => NamedButton::MouseMiddle,
+            MouseButton::Right => NamedButton::MouseRight,
+            _ => return,
+        };
+        set_button(&mut self.current_states, Button::Named(button), pressed);
+    }
 
     pub fn triggered(&self, button: Button) -> bool {
         self.current_states.contains(&button) && !self.last_states.contains(&button)
@@ -183,7 +509,266 @@ impl Buttons {
         self.current_states.contains(&button)
     }
 
+    /// True on the frame `button` is pressed, then repeatedly while it's held, like RGSS.
     pub fn repeat(&self, button: Button) -> bool {
-        self.repeats.contains(&button)
+        let Some(&frames) = self.hold_frames.get(&button) else {
+            return false;
+        };
+        let held_for = frames - 1;
+        held_for == 0
+            || (held_for >= REPEAT_DELAY
+                && (held_for - REPEAT_DELAY).is_multiple_of(REPEAT_INTERVAL))
+    }
+
+    /// The direction being pressed (2, 4, 6 or 8), or 0 if there isn't one.
+    ///
+    /// The most recently pressed direction wins, and opposite directions cancel out.
+    pub fn dir4(&self) -> u8 {
+        let flags = DIRS
+            .iter()
+            .zip(DIR_FLAGS)
+            .filter(|(dir, _)| self.pressed(Button::KeyBind(**dir)))
+            .fold(0, |flags, (_, flag)| flags | flag);
+        if DEAD_DIR_FLAGS.contains(&flags) {
+            return 0;
+        }
+
+        self.directions.last().map_or(0, |&dir| dir as u8)
+    }
+
+    /// Like [`Buttons::dir4`], but combines the direction with the most recently pressed perpendicular one
+    /// into a diagonal (1, 3, 7 or 9).
+    pub fn dir8(&self) -> u8 {
+        let Some(&dir) = self.directions.last().filter(|_| self.dir4() != 0) else {
+            return 0;
+        };
+
+        let others = &OTHER_DIRS[dir_index(dir)];
+        self.directions
+            .iter()
+            .rev()
+            .filter(|other| others.contains(other))
+            .map(|&other| DIR_COMBOS[dir_index(dir)][dir_index(other)])
+            .find(|&combo| combo != 0)
+            .unwrap_or(dir as u8)
+    }
+}
+
+fn set_button(states: &mut States, button: Button, held: bool) {
+    if held {
+        states.insert(button);
+    } else {
+        states.remove(&button);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Z: PhysicalInput = PhysicalInput::Key(KeyCode::KeyZ);
+    const SPACE: PhysicalInput = PhysicalInput::Key(KeyCode::Space);
+
+    #[test]
+    fn default_directions_are_distinct() {
+        let bindings = Bindings::default();
+        for keybind in [KeyBind::Down, KeyBind::Left, KeyBind::Right, KeyBind::Up] {
+            for &input in bindings.inputs(keybind) {
+                assert_eq!(bindings.keybinds(input), [keybind]);
+            }
+        }
+        assert_eq!(
+            bindings.keybinds(PhysicalInput::Key(KeyCode::ArrowLeft)),
+            [KeyBind::Left]
+        );
+    }
+
+    #[test]
+    fn action_stays_pressed_until_every_key_is_released() {
+        let mut buttons = Buttons::default();
+        let action = Button::KeyBind(KeyBind::Action);
+
+        buttons.process_input(Z, true);
+        buttons.process_input(SPACE, true);
+        assert!(buttons.pressed(action));
+
+        buttons.process_input(Z, false);
+        assert!(buttons.pressed(action));
+        buttons.process_input(SPACE, false);
+        assert!(!buttons.pressed(action));
+    }
+
+    #[test]
+    fn unbound_keys_are_reported_directly() {
+        let mut buttons = Buttons::default();
+        buttons.process_input(PhysicalInput::Key(KeyCode::F9), true);
+        buttons.process_input(PhysicalInput::Key(KeyCode::KeyM), true);
+        assert!(buttons.pressed(Button::Named(NamedButton::F9)));
+        assert!(buttons.pressed(Button::KeyCode(KeyCode::KeyM)));
+    }
+
+    fn press(buttons: &mut Buttons, key: KeyCode, pressed: bool) {
+        buttons.start_frame();
+        buttons.process_input(PhysicalInput::Key(key), pressed);
+        buttons.end_frame();
+    }
+
+    #[test]
+    fn most_recent_direction_wins() {
+        let mut buttons = Buttons::default();
+        press(&mut buttons, KeyCode::ArrowLeft, true);
+        assert_eq!(buttons.dir4(), 4);
+        press(&mut buttons, KeyCode::ArrowUp, true);
+        assert_eq!(buttons.dir4(), 8);
+        assert_eq!(buttons.dir8(), 7);
+
+        press(&mut buttons, KeyCode::ArrowUp, false);
+        assert_eq!(buttons.dir4(), 4);
+        assert_eq!(buttons.dir8(), 4);
+    }
+
+    #[test]
+    fn opposite_directions_cancel() {
+        let mut buttons = Buttons::default();
+        press(&mut buttons, KeyCode::ArrowLeft, true);
+        press(&mut buttons, KeyCode::ArrowRight, true);
+        assert_eq!(buttons.dir4(), 0);
+        assert_eq!(buttons.dir8(), 0);
+
+        // a third direction breaks the tie
+        press(&mut buttons, KeyCode::ArrowDown, true);
+        assert_eq!(buttons.dir4(), 2);
+        assert_eq!(buttons.dir8(), 3);
+    }
+
+    #[test]
+    fn repeat_is_timed_in_frames() {
+        let mut buttons = Buttons::default();
+        let action = Button::KeyBind(KeyBind::Action);
+        let mut repeats = Vec::new();
+        for frame in 0..30 {
+            buttons.start_frame();
+            if frame == 0 {
+                buttons.process_input(Z, true);
+            }
+            buttons.end_frame();
+            if buttons.repeat(action) {
+                repeats.push(frame);
+            }
+        }
+        assert_eq!(repeats, [0, 15, 19, 23, 27]);
+
+        press(&mut buttons, KeyCode::KeyZ, false);
+        assert!(!buttons.repeat(action));
+    }
+
+    #[test]
+    fn simulated_presses_behave_like_real_ones() {
+        let mut buttons = Buttons::default();
+        let action = Button::KeyBind(KeyBind::Action);
+        buttons.start_frame();
+        buttons.process_simulated(action, true);
+        buttons.end_frame();
+        assert!(buttons.triggered(action));
+        assert!(buttons.repeat(action));
+
+        // a real key keeps it held after the simulated press ends
+        press(&mut buttons, KeyCode::KeyZ, true);
+        assert!(!buttons.triggered(action));
+        buttons.start_frame();
+        buttons.process_simulated(action, false);
+        buttons.end_frame();
+        assert!(buttons.pressed(action));
+        press(&mut buttons, KeyCode::KeyZ, false);
+        assert!(!buttons.pressed(action));
+    }
+
+    #[test]
+    fn simulated_named_buttons_press_their_bindings() {
+        let mut buttons = Buttons::default();
+        let shift = Button::Named(NamedButton::Shift);
+        let run = Button::KeyBind(KeyBind::Run);
+        buttons.start_frame();
+        buttons.process_simulated(shift, true);
+        buttons.end_frame();
+        assert!(buttons.triggered(shift));
+        assert!(buttons.triggered(run));
+
+        // the real key is tracked separately, so it keeps both held
+        press(&mut buttons, KeyCode::ShiftLeft, true);
+        buttons.start_frame();
+        buttons.process_simulated(shift, false);
+        buttons.end_frame();
+        assert!(buttons.pressed(shift));
+        assert!(buttons.pressed(run));
+        press(&mut buttons, KeyCode::ShiftLeft, false);
+        assert!(!buttons.pressed(shift));
+        assert!(!buttons.pressed(run));
+    }
+
+    #[test]
+    fn gamepad_buttons_repeat() {
+        let mut buttons = Buttons::default();
+        let pad = PhysicalInput::Gamepad(GamepadButton::DPadDown);
+        buttons.start_frame();
+        buttons.process_input(pad, true);
+        buttons.end_frame();
+        assert!(buttons.repeat(Button::KeyBind(KeyBind::Down)));
+    }
+
+    #[test]
+    fn mouse_buttons() {
+        use winit::event::MouseButton;
+
+        let mut buttons = Buttons::default();
+        buttons.start_frame();
+        buttons.process_mouse(MouseButton::Left, true);
+        assert!(buttons.triggered(Button::Named(NamedButton::MouseLeft)));
+
+        buttons.start_frame();
+        assert!(!buttons.triggered(Button::Named(NamedButton::MouseLeft)));
+        assert!(buttons.pressed(Button::Named(NamedButton::MouseLeft)));
+        buttons.process_mouse(MouseButton::Left, false);
+        assert!(!buttons.pressed(Button::Named(NamedButton::MouseLeft)));
+    }
+
+    #[test]
+    fn shift_is_pressed_even_when_bound() {
+        let mut buttons = Buttons::default();
+        press(&mut buttons, KeyCode::ShiftLeft, true);
+        assert!(buttons.pressed(Button::Named(NamedButton::Shift)));
+        assert!(buttons.pressed(Button::KeyBind(KeyBind::Run)));
+    }
+
+    #[test]
+    fn gamepad_bindings_from_config() {
+        let config: crate::InputConfig =
+            toml::from_str(r#"gamepad_bindings = { action = ["east"], cancel = ["south"] }"#)
+                .unwrap();
+        let mut bindings = Bindings::default();
+        for (&keybind, buttons) in &config.gamepad_bindings {
+            bindings.set_gamepad_buttons(keybind, buttons);
+        }
+
+        let pad = |button| PhysicalInput::Gamepad(button);
+        assert_eq!(
+            bindings.keybinds(pad(GamepadButton::East)),
+            [KeyBind::Action]
+        );
+        assert_eq!(
+            bindings.keybinds(pad(GamepadButton::South)),
+            [KeyBind::Cancel]
+        );
+        // keys are left alone
+        assert_eq!(bindings.keybinds(Z), [KeyBind::Action]);
+    }
+
+    #[test]
+    fn rebinding_updates_the_reverse_table() {
+        let mut bindings = Bindings::default();
+        bindings.unbind(KeyBind::Action, Z);
+        bindings.bind(KeyBind::Cancel, Z);
+        assert_eq!(bindings.keybinds(Z), [KeyBind::Cancel]);
+        assert_eq!(bindings.keybinds(SPACE), [KeyBind::Action]);
     }
 }
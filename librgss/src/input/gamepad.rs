@@ -0,0 +1,177 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+use gilrs::{Axis, Event, EventType, GamepadId, Gilrs};
+use parking_lot::Mutex;
+
+use super::buttons::{Buttons, GamepadButton, PhysicalInput};
+
+const DEFAULT_DEADZONE: f32 = 0.25;
+
+/// Polls connected gamepads. Controllers can be plugged in and out while the game is running.
+pub(super) struct Gamepads {
+    /// `None` if gamepads aren't supported on this platform (or failed to initialize).
+    ///
+    /// Gilrs isn't `Sync`, but we only ever use it mutably, so the mutex is never actually locked.
+    gilrs: Option<Mutex<Gilrs>>,
+    deadzone: f32,
+    /// What each gamepad is holding, so a button shared by two gamepads stays pressed until both release it.
+    held: HashMap<GamepadId, HashSet<GamepadButton>>,
+}
+
+impl Gamepads {
+    pub fn new(deadzone: Option<f32>) -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(error) => {
+                eprintln!("Gamepads are unavailable: {error}");
+                None
+            }
+        };
+
+        if let Some(gilrs) = &gilrs {
+            for (_, gamepad) in gilrs.gamepads() {
+                println!("Found gamepad {}", gamepad.name());
+            }
+        }
+
+        Self {
+            gilrs: gilrs.map(Mutex::new),
+            deadzone: deadzone.unwrap_or(DEFAULT_DEADZONE).clamp(0.0, 1.0),
+            held: HashMap::new(),
+        }
+    }
+
+    /// Handles every gamepad event since the last update.
+    pub fn update(&mut self, buttons: &mut Buttons) {
+        loop {
+            let Some(gilrs) = self.gilrs.as_mut().map(Mutex::get_mut) else {
+                return;
+            };
+            let Some(event) = gilrs.next_event() else {
+                return;
+            };
+            if event.event == EventType::Connected {
+                println!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+            }
+            self.handle_event(buttons, event);
+        }
+    }
+
+    fn handle_event(&mut self, buttons: &mut Buttons, event: Event) {
+        let id = event.id;
+        match event.event {
+            EventType::ButtonPressed(button, _) => {
+                if let Some(button) = map_button(button) {
+                    self.set(buttons, id, button, true);
+                }
+            }
+            EventType::ButtonReleased(button, _) => {
+                if let Some(button) = map_button(button) {
+                    self.set(buttons, id, button, false);
+                }
+            }
+            EventType::AxisChanged(axis, value, _) => {
+                if let Some((negative, positive)) = axis_buttons(axis) {
+                    self.set(buttons, id, negative, value < -self.deadzone);
+                    self.set(buttons, id, positive, value > self.deadzone);
+                }
+            }
+            EventType::Disconnected => {
+                println!("Gamepad disconnected");
+                let held = self.held.remove(&id).unwrap_or_default();
+                for button in held {
+                    self.release_if_unheld(buttons, button);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set(&mut self, buttons: &mut Buttons, id: GamepadId, button: GamepadButton, pressed: bool) {
+        let held = self.held.entry(id).or_default();
+        let changed = if pressed {
+            held.insert(button)
+        } else {
+            held.remove(&button)
+        };
+        if !changed {
+            return;
+        }
+
+        if pressed {
+            buttons.process_input(PhysicalInput::Gamepad(button), true);
+        } else {
+            self.release_if_unheld(buttons, button);
+        }
+    }
+
+    fn release_if_unheld(&self, buttons: &mut Buttons, button: GamepadButton) {
+        if !self.held.values().any(|held| held.contains(&button)) {
+            buttons.process_input(PhysicalInput::Gamepad(button), false);
+        }
+    }
+
+    /// Forgets what every gamepad is holding, for soft resets.
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+fn map_button(button: gilrs::Button) -> Option<GamepadButton> {
+    use gilrs::Button;
+
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftShoulder,
+        Button::RightTrigger => GamepadButton::RightShoulder,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+/// The buttons an axis presses when pushed towards its negative and positive ends.
+fn axis_buttons(axis: Axis) -> Option<(GamepadButton, GamepadButton)> {
+    Some(match axis {
+        Axis::LeftStickX => (GamepadButton::LeftStickLeft, GamepadButton::LeftStickRight),
+        // up is positive
+        Axis::LeftStickY => (GamepadButton::LeftStickDown, GamepadButton::LeftStickUp),
+        Axis::RightStickX => (
+            GamepadButton::RightStickLeft,
+            GamepadButton::RightStickRight,
+        ),
+        Axis::RightStickY => (GamepadButton::RightStickDown, GamepadButton::RightStickUp),
+        // some gamepads report their d-pad as a pair of axes
+        Axis::DPadX => (GamepadButton::DPadLeft, GamepadButton::DPadRight),
+        Axis::DPadY => (GamepadButton::DPadDown, GamepadButton::DPadUp),
+        _ => return None,
+    })
+}
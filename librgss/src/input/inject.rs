@@ -0,0 +1,84 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::buttons::{Button, Buttons};
+
+/// Synthetic presses and releases waiting to be applied, for scripts that drive the game without a window.
+#[derive(Debug, Default)]
+pub(super) struct Injector {
+    /// In the order they were queued, so a press and release due on the same frame are applied in order.
+    queue: Vec<Injection>,
+}
+
+#[derive(Debug)]
+struct Injection {
+    /// How many more updates until this is applied.
+    delay: u32,
+    button: Button,
+    pressed: bool,
+}
+
+impl Injector {
+    pub fn push(&mut self, button: Button, pressed: bool, delay: u32) {
+        self.queue.push(Injection {
+            delay,
+            button,
+            pressed,
+        });
+    }
+
+    /// Applies everything due this frame, and counts down the rest.
+    pub fn update(&mut self, buttons: &mut Buttons) {
+        self.queue.retain_mut(|injection| {
+            if injection.delay == 0 {
+                buttons.process_simulated(injection.button, injection.pressed);
+                false
+            } else {
+                injection.delay -= 1;
+                true
+            }
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyBind;
+
+    #[test]
+    fn presses_are_applied_on_time() {
+        let mut buttons = Buttons::default();
+        let mut injector = Injector::default();
+        let cancel = Button::KeyBind(KeyBind::Cancel);
+        injector.push(cancel, true, 1);
+        injector.push(cancel, false, 3);
+
+        let mut pressed = Vec::new();
+        for _ in 0..5 {
+            buttons.start_frame();
+            injector.update(&mut buttons);
+            buttons.end_frame();
+            pressed.push(buttons.pressed(cancel));
+        }
+        assert_eq!(pressed, [false, true, true, false, false]);
+    }
+}
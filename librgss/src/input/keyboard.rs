@@ -0,0 +1,340 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use winit::{
+    event::{Ime, KeyEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+/// How many entries mkxp-z's `Input.raw_key_states` has, one for each SDL scancode.
+const SCANCODE_COUNT: usize = 512;
+
+/// Every key held down and the text typed, for scripts that read the keyboard directly.
+#[derive(Debug, Default)]
+pub(super) struct Keyboard {
+    held: HashSet<KeyCode>,
+    /// Whether typed text is collected. Scripts turn this on for text boxes.
+    text_input: bool,
+    /// Text committed this frame, either typed directly or through an IME.
+    text: String,
+    /// Text committed since the scripts last took it.
+    buffer: String,
+    /// Text the IME is still composing, which hasn't been committed yet.
+    composition: String,
+}
+
+impl Keyboard {
+    pub fn start_frame(&mut self) {
+        self.text.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.text_input = false;
+        self.text.clear();
+        self.buffer.clear();
+        self.composition.clear();
+    }
+
+    pub fn process_key(&mut self, event: &KeyEvent) {
+        if let PhysicalKey::Code(key) = event.physical_key {
+            if event.state.is_pressed() {
+                self.held.insert(key);
+            } else {
+                self.held.remove(&key);
+            }
+        }
+
+        // text is repeated by the OS while a key is held, which is what a text box wants
+        if let (true, Some(text)) = (event.state.is_pressed(), &event.text) {
+            self.push_text(text);
+        }
+    }
+
+    pub fn process_ime(&mut self, ime: Ime) {
+        match ime {
+            Ime::Commit(text) => {
+                self.push_text(&text);
+                self.composition.clear();
+            }
+            Ime::Preedit(text, _) => self.composition = text,
+            Ime::Disabled => self.composition.clear(),
+            Ime::Enabled => {}
+        }
+    }
+
+    /// Adds typed text if text input is on, leaving out control characters like backspace and enter (which scripts see as keys).
+    fn push_text(&mut self, text: &str) {
+        if !self.text_input {
+            return;
+        }
+        let start = self.text.len();
+        self.text.extend(text.chars().filter(|c| !c.is_control()));
+        self.buffer.push_str(&self.text[start..]);
+    }
+
+    /// Replaces this frame's state with a recorded one.
+    pub fn replay(&mut self, keys: &[KeyCode], text: &str) {
+        self.held.clear();
+        self.held.extend(keys.iter().copied());
+        self.text.clear();
+        self.composition.clear();
+        self.push_text(text);
+    }
+
+    pub fn text_input(&self) -> bool {
+        self.text_input
+    }
+
+    /// Turns text input on or off. Anything typed while it was on and not taken yet is thrown away when it's turned off.
+    pub fn set_text_input(&mut self, enabled: bool) {
+        self.text_input = enabled;
+        if !enabled {
+            self.buffer.clear();
+            self.composition.clear();
+        }
+    }
+
+    /// Takes the text typed since the last call.
+    pub fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+
+    pub fn held(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.held.iter().copied()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn composition(&self) -> &str {
+        &self.composition
+    }
+
+    /// Which keys are held, indexed by SDL scancode like mkxp-z.
+    pub fn raw_key_states(&self) -> Vec<bool> {
+        let mut states = vec![false; SCANCODE_COUNT];
+        for index in self.held.iter().copied().filter_map(sdl_scancode) {
+            states[index] = true;
+        }
+        states
+    }
+}
+
+const LETTERS: [KeyCode; 26] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+];
+// SDL puts 0 after 9
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+];
+const NUMPAD_DIGITS: [KeyCode; 10] = [
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::Numpad0,
+];
+const FUNCTION_KEYS: [KeyCode; 12] = [
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+const HIGH_FUNCTION_KEYS: [KeyCode; 12] = [
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+];
+
+/// The SDL scancode for a key, which is its USB HID usage ID. Keys SDL doesn't know about are left out.
+fn sdl_scancode(key: KeyCode) -> Option<usize> {
+    let runs: [(&[KeyCode], usize); 5] = [
+        (&LETTERS, 4),
+        (&DIGITS, 30),
+        (&FUNCTION_KEYS, 58),
+        (&NUMPAD_DIGITS, 89),
+        (&HIGH_FUNCTION_KEYS, 104),
+    ];
+    if let Some(code) = runs.iter().find_map(|(keys, first)| {
+        let index = keys.iter().position(|&k| k == key)?;
+        Some(first + index)
+    }) {
+        return Some(code);
+    }
+
+    let code = match key {
+        KeyCode::Enter => 40,
+        KeyCode::Escape => 41,
+        KeyCode::Backspace => 42,
+        KeyCode::Tab => 43,
+        KeyCode::Space => 44,
+        KeyCode::Minus => 45,
+        KeyCode::Equal => 46,
+        KeyCode::BracketLeft => 47,
+        KeyCode::BracketRight => 48,
+        KeyCode::Backslash => 49,
+        KeyCode::Semicolon => 51,
+        KeyCode::Quote => 52,
+        KeyCode::Backquote => 53,
+        KeyCode::Comma => 54,
+        KeyCode::Period => 55,
+        KeyCode::Slash => 56,
+        KeyCode::CapsLock => 57,
+        KeyCode::PrintScreen => 70,
+        KeyCode::ScrollLock => 71,
+        KeyCode::Pause => 72,
+        KeyCode::Insert => 73,
+        KeyCode::Home => 74,
+        KeyCode::PageUp => 75,
+        KeyCode::Delete => 76,
+        KeyCode::End => 77,
+        KeyCode::PageDown => 78,
+        KeyCode::ArrowRight => 79,
+        KeyCode::ArrowLeft => 80,
+        KeyCode::ArrowDown => 81,
+        KeyCode::ArrowUp => 82,
+        KeyCode::NumLock => 83,
+        KeyCode::NumpadDivide => 84,
+        KeyCode::NumpadMultiply => 85,
+        KeyCode::NumpadSubtract => 86,
+        KeyCode::NumpadAdd => 87,
+        KeyCode::NumpadEnter => 88,
+        KeyCode::NumpadDecimal => 99,
+        KeyCode::IntlBackslash => 100,
+        KeyCode::ContextMenu => 101,
+        KeyCode::Power => 102,
+        KeyCode::NumpadEqual => 103,
+        KeyCode::ControlLeft => 224,
+        KeyCode::ShiftLeft => 225,
+        KeyCode::AltLeft => 226,
+        KeyCode::SuperLeft => 227,
+        KeyCode::ControlRight => 228,
+        KeyCode::ShiftRight => 229,
+        KeyCode::AltRight => 230,
+        KeyCode::SuperRight => 231,
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scancodes_match_sdl() {
+        assert_eq!(sdl_scancode(KeyCode::KeyA), Some(4));
+        assert_eq!(sdl_scancode(KeyCode::KeyZ), Some(29));
+        assert_eq!(sdl_scancode(KeyCode::Digit0), Some(39));
+        assert_eq!(sdl_scancode(KeyCode::F12), Some(69));
+        assert_eq!(sdl_scancode(KeyCode::Numpad0), Some(98));
+        assert_eq!(sdl_scancode(KeyCode::ShiftRight), Some(229));
+        assert_eq!(sdl_scancode(KeyCode::Fn), None);
+    }
+
+    #[test]
+    fn ime_text_is_committed() {
+        let mut keyboard = Keyboard::default();
+        keyboard.set_text_input(true);
+        keyboard.process_ime(Ime::Preedit("か".to_string(), None));
+        assert_eq!(keyboard.composition(), "か");
+        assert_eq!(keyboard.text(), "");
+
+        keyboard.process_ime(Ime::Commit("日本\u{8}".to_string()));
+        assert_eq!(keyboard.composition(), "");
+        assert_eq!(keyboard.text(), "日本");
+
+        keyboard.start_frame();
+        assert_eq!(keyboard.text(), "");
+        // scripts take the text whenever they're ready for it
+        assert_eq!(keyboard.take_text(), "日本");
+        assert_eq!(keyboard.take_text(), "");
+    }
+
+    #[test]
+    fn text_is_only_collected_while_text_input_is_on() {
+        let mut keyboard = Keyboard::default();
+        keyboard.process_ime(Ime::Commit("a".to_string()));
+        assert_eq!(keyboard.take_text(), "");
+
+        keyboard.set_text_input(true);
+        keyboard.process_ime(Ime::Commit("b".to_string()));
+        keyboard.set_text_input(false);
+        assert_eq!(keyboard.text(), "b");
+        assert_eq!(keyboard.take_text(), "");
+    }
+}
@@ -15,44 +15,130 @@
 // You should have received a copy of the GNU General Public License
 // along with Sapphire.  If not, see <http://www.gnu.org/licenses/>.
 
-use winit::event::{Event, WindowEvent};
+use winit::{
+    event::{Event, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
 
-use crate::{event_loop::UserEvent, Events};
+use color_eyre::eyre::WrapErr;
+
+use crate::{event_loop::UserEvent, Events, InputConfig};
 
 mod buttons;
-pub use buttons::{Button, KeyBind, NamedButton};
+mod gamepad;
+use gamepad::Gamepads;
+
+mod inject;
+use inject::Injector;
+
+mod keyboard;
+use keyboard::Keyboard;
+
+mod mouse;
+use mouse::Mouse;
+
+mod replay;
+use replay::{new_seed, Frame, Recorder, Replayer};
+
+pub use buttons::{Bindings, Button, GamepadButton, KeyBind, NamedButton, PhysicalInput};
+pub use replay::ReplayError;
 
 pub struct Input {
     events: Events,
     buttons: buttons::Buttons,
+    gamepads: Gamepads,
+    mouse: Mouse,
+    keyboard: Keyboard,
+    injector: Injector,
+    recorder: Option<Recorder>,
+    replayer: Option<Replayer>,
+    rng_seed: Option<u64>,
     exited: bool,
+    reset_requested: bool,
 }
 
 // TODO add an optional pump_events feature that uses winit::EventLoopExtPumpEvents that allows running bindings on the main thread
 impl Input {
-    pub fn new(events: Events) -> Self {
-        Self {
+    /// `screen_size` is the size of the game's screen (see [`crate::Graphics::screen_size`]), which the mouse position is mapped to.
+    pub fn new(
+        events: Events,
+        config: &InputConfig,
+        screen_size: (u32, u32),
+    ) -> color_eyre::Result<Self> {
+        let mut buttons = buttons::Buttons::default();
+        for (&keybind, gamepad_buttons) in &config.gamepad_bindings {
+            buttons
+                .bindings
+                .set_gamepad_buttons(keybind, gamepad_buttons);
+        }
+
+        let replayer = config.replay.as_deref().map(Replayer::open).transpose()?;
+        // a replay uses the recording's seed, so the scripts make the same random choices
+        let rng_seed = match &replayer {
+            Some(replayer) => Some(replayer.seed),
+            None => config.record.is_some().then(new_seed),
+        };
+        let recorder = config
+            .record
+            .as_deref()
+            .map(|path| {
+                Recorder::create(path, rng_seed.unwrap_or_default())
+                    .wrap_err_with(|| format!("Failed to create {path}"))
+            })
+            .transpose()?;
+
+        Ok(Self {
             events,
-            buttons: buttons::Buttons::default(),
+            buttons,
+            gamepads: Gamepads::new(config.gamepad_deadzone),
+            mouse: Mouse::new(screen_size),
+            keyboard: Keyboard::default(),
+            injector: Injector::default(),
+            recorder,
+            replayer,
+            rng_seed,
             exited: false,
-        }
+            reset_requested: false,
+        })
     }
 
     /// Process all incoming events from the event loop, updating all input state.
     pub fn update(&mut self) {
         self.buttons.start_frame();
+        self.mouse.start_frame();
+        self.keyboard.start_frame();
+        let replaying = self.replayer.is_some();
         for event in self.events.event_reciever.try_iter() {
             match event {
                 // TODO handle window events
                 Event::WindowEvent { event, .. } => {
                     //
                     match event {
-                        WindowEvent::KeyboardInput { event, .. } => self.buttons.process_key(event),
-                        WindowEvent::MouseInput { button, state, .. } if state.is_pressed() => {
-                            self.buttons.process_mouse(button)
-                        }
                         WindowEvent::Destroyed => self.exit(), // TODO handle properly
                         WindowEvent::CloseRequested => self.exit(), // TODO handle oneshot close stuff
+                        // a replay ignores the player entirely, apart from letting them close the window
+                        _ if replaying => {}
+                        // F12 is reserved for soft resetting, and is never seen by scripts
+                        WindowEvent::KeyboardInput { event, .. }
+                            if event.physical_key == PhysicalKey::Code(KeyCode::F12)
+                                && event.state.is_pressed()
+                                && !event.repeat =>
+                        {
+                            self.reset_requested = true;
+                        }
+                        WindowEvent::KeyboardInput { event, .. }
+                            if event.physical_key == PhysicalKey::Code(KeyCode::F12) => {}
+                        WindowEvent::KeyboardInput { event, .. } => {
+                            self.keyboard.process_key(&event);
+                            self.buttons.process_key(event)
+                        }
+                        WindowEvent::Ime(ime) => self.keyboard.process_ime(ime),
+                        WindowEvent::MouseInput { button, state, .. } => {
+                            self.buttons.process_mouse(button, state.is_pressed())
+                        }
+                        WindowEvent::CursorMoved { position, .. } => self.mouse.moved(position),
+                        WindowEvent::MouseWheel { delta, .. } => self.mouse.scrolled(delta),
+                        WindowEvent::Resized(size) => self.mouse.resized(size),
                         _ => {}
                     }
                 }
@@ -60,6 +146,61 @@ impl Input {
                 _ => {}
             }
         }
+        if let Some(replayer) = &mut self.replayer {
+            match replayer.next_frame() {
+                Some(frame) => {
+                    self.buttons.replay(&frame.pressed);
+                    self.mouse.replay(frame.mouse, frame.scroll);
+                    self.keyboard.replay(&frame.keys, &frame.text);
+                    self.reset_requested |= frame.reset;
+                }
+                None => {
+                    eprintln!("Finished replaying {}", replayer.path);
+                    self.replayer = None;
+                    self.exit();
+                }
+            }
+        } else {
+            self.gamepads.update(&mut self.buttons);
+            self.injector.update(&mut self.buttons);
+        }
+        self.buttons.end_frame();
+
+        if let Some(recorder) = &mut self.recorder {
+            let frame = Frame {
+                frame: recorder.frames(),
+                pressed: self.buttons.pressed_buttons().collect(),
+                mouse: self.mouse.position(),
+                scroll: self.mouse.scroll(),
+                keys: self.keyboard.held().collect(),
+                text: self.keyboard.text().to_string(),
+                reset: self.reset_requested,
+            };
+            if let Err(e) = recorder.record(&frame) {
+                eprintln!("Failed to record input, stopping recording: {e}");
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// What the scripts' random number generator should be seeded with, if input is being recorded or replayed.
+    pub fn rng_seed(&self) -> Option<u64> {
+        self.rng_seed
+    }
+
+    /// Stops recording and checks that a replay was played to the end.
+    ///
+    /// Should be called once the game has stopped, before exiting.
+    pub fn finish(&mut self) -> color_eyre::Result<()> {
+        if let Some(recorder) = self.recorder.take() {
+            recorder
+                .finish()
+                .wrap_err("Failed to finish input recording")?;
+        }
+        if let Some(replayer) = self.replayer.take() {
+            replayer.finish()?;
+        }
+        Ok(())
     }
 
     /// Notifies the event loop that we'd like to exit.
@@ -74,6 +215,35 @@ impl Input {
         self.exited
     }
 
+    /// Returns true if the player has pressed F12 since the last call, clearing the request.
+    ///
+    /// Bindings should respond to this by raising `RGSSReset`.
+    pub fn take_reset_request(&mut self) -> bool {
+        std::mem::take(&mut self.reset_requested)
+    }
+
+    /// Clears all button state, so nothing held before a soft reset carries over.
+    pub fn reset(&mut self) {
+        self.buttons.clear();
+        self.gamepads.clear();
+        self.injector.clear();
+        self.keyboard.clear();
+        self.reset_requested = false;
+    }
+
+    /// Queues a synthetic press or release of `button`, applied `delay` updates from now (0 being the next update).
+    ///
+    /// This goes through the same state as real input, so `triggered` and `repeat` behave the same.
+    pub fn simulate(&mut self, button: Button, pressed: bool, delay: u32) {
+        self.injector.push(button, pressed, delay);
+    }
+
+    /// Presses `button` after `delay` updates, and releases it `frames` updates after that.
+    pub fn simulate_press(&mut self, button: Button, frames: u32, delay: u32) {
+        self.simulate(button, true, delay);
+        self.simulate(button, false, delay.saturating_add(frames.max(1)));
+    }
+
     pub fn triggered(&self, button: Button) -> bool {
         self.buttons.triggered(button)
     }
@@ -85,4 +255,49 @@ impl Input {
     pub fn repeat(&self, button: Button) -> bool {
         self.buttons.repeat(button)
     }
+
+    /// The cursor position on the game's screen, which is stretched over the window if their sizes differ.
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse.position()
+    }
+
+    /// How many lines the mouse wheel scrolled this frame, with positive values scrolling up.
+    pub fn mouse_scroll(&self) -> f64 {
+        self.mouse.scroll()
+    }
+
+    /// Which keys are held, indexed by SDL scancode like mkxp-z. Keys bound to buttons are included.
+    pub fn raw_key_states(&self) -> Vec<bool> {
+        self.keyboard.raw_key_states()
+    }
+
+    /// Whether typed text is being collected for [`Input::take_text`].
+    pub fn text_input(&self) -> bool {
+        self.keyboard.text_input()
+    }
+
+    /// Turns collecting typed text on or off. The window should only allow IMEs while it's on (see [`crate::Graphics::set_ime_allowed`]).
+    pub fn set_text_input(&mut self, enabled: bool) {
+        self.keyboard.set_text_input(enabled);
+    }
+
+    /// Takes the text typed since the last call, including text committed by an IME.
+    pub fn take_text(&mut self) -> String {
+        self.keyboard.take_text()
+    }
+
+    /// Text the IME is composing, which will show up in [`Input::take_text`] once it's committed.
+    pub fn text_composition(&self) -> &str {
+        self.keyboard.composition()
+    }
+
+    /// The direction being pressed (2, 4, 6 or 8), or 0 if there isn't one.
+    pub fn dir4(&self) -> u8 {
+        self.buttons.dir4()
+    }
+
+    /// The direction being pressed, including diagonals (1, 3, 7 or 9), or 0 if there isn't one.
+    pub fn dir8(&self) -> u8 {
+        self.buttons.dir8()
+    }
 }
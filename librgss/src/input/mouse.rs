@@ -0,0 +1,134 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::MouseScrollDelta,
+};
+
+/// Roughly how many pixels of touchpad scrolling make up one line of wheel scrolling.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Where the cursor is, and how far the wheel has scrolled this frame.
+pub(super) struct Mouse {
+    screen_size: PhysicalSize<u32>,
+    /// The window is created at the screen's size and can't be resized, but the window manager may still have its way.
+    window_size: PhysicalSize<u32>,
+    /// In window pixels. The last position is kept when the cursor leaves the window.
+    position: PhysicalPosition<f64>,
+    /// In lines, with positive values scrolling up.
+    scroll: f64,
+}
+
+impl Mouse {
+    pub fn new(screen_size: (u32, u32)) -> Self {
+        let screen_size = PhysicalSize::new(screen_size.0, screen_size.1);
+        Self {
+            screen_size,
+            window_size: screen_size,
+            position: PhysicalPosition::new(0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    pub fn start_frame(&mut self) {
+        self.scroll = 0.0;
+    }
+
+    pub fn resized(&mut self, size: PhysicalSize<u32>) {
+        self.window_size = size;
+    }
+
+    pub fn moved(&mut self, position: PhysicalPosition<f64>) {
+        self.position = position;
+    }
+
+    pub fn scrolled(&mut self, delta: MouseScrollDelta) {
+        self.scroll += match delta {
+            MouseScrollDelta::LineDelta(_, y) => y as f64,
+            MouseScrollDelta::PixelDelta(position) => position.y / PIXELS_PER_LINE,
+        };
+    }
+
+    /// Puts the cursor at a recorded position (in game pixels) and sets this frame's scrolling.
+    pub fn replay(&mut self, position: (i32, i32), scroll: f64) {
+        self.window_size = self.screen_size;
+        self.position = PhysicalPosition::new(position.0 as f64, position.1 as f64);
+        self.scroll = scroll;
+    }
+
+    /// The cursor position in game pixels. This can be outside the screen while a button is held and the cursor is dragged out of the window.
+    pub fn position(&self) -> (i32, i32) {
+        let (x, y) = window_to_game(self.position, self.window_size, self.screen_size);
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    /// How many lines the wheel scrolled this frame, with positive values scrolling up.
+    pub fn scroll(&self) -> f64 {
+        self.scroll
+    }
+}
+
+/// Maps a position in the window to the game's screen.
+///
+/// The surface is always the size of the screen and is presented over the whole window, so this is
+/// one to one unless the window ended up a different size, in which case the screen is stretched over it.
+fn window_to_game(
+    position: PhysicalPosition<f64>,
+    window_size: PhysicalSize<u32>,
+    screen_size: PhysicalSize<u32>,
+) -> (f64, f64) {
+    let scale_x = screen_size.width as f64 / window_size.width.max(1) as f64;
+    let scale_y = screen_size.height as f64 / window_size.height.max(1) as f64;
+    (position.x * scale_x, position.y * scale_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_maps_directly() {
+        let mut mouse = Mouse::new((640, 480));
+        mouse.moved(PhysicalPosition::new(100.5, 200.0));
+        assert_eq!(mouse.position(), (100, 200));
+
+        // RGSS2 and 3 have a smaller screen, but the window is made to match
+        let mut mouse = Mouse::new((544, 416));
+        mouse.moved(PhysicalPosition::new(543.0, 415.0));
+        assert_eq!(mouse.position(), (543, 415));
+    }
+
+    #[test]
+    fn resized_window_stretches_the_screen() {
+        let mut mouse = Mouse::new((640, 480));
+        mouse.resized(PhysicalSize::new(1280, 720));
+        mouse.moved(PhysicalPosition::new(640.0, 360.0));
+        assert_eq!(mouse.position(), (320, 240));
+        mouse.moved(PhysicalPosition::new(1280.0, 720.0));
+        assert_eq!(mouse.position(), (640, 480));
+    }
+
+    #[test]
+    fn replays_in_game_pixels() {
+        let mut mouse = Mouse::new((544, 416));
+        mouse.resized(PhysicalSize::new(1088, 832));
+        mouse.replay((12, -3), 1.0);
+        assert_eq!(mouse.position(), (12, -3));
+        assert_eq!(mouse.scroll(), 1.0);
+    }
+}
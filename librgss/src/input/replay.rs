@@ -0,0 +1,382 @@
+// Copyright (C) 2024 Lily Lyons
+//
+// This file is part of sapphire.
+//
+// sapphire is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// sapphire is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with sapphire.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use winit::keyboard::KeyCode;
+
+use super::buttons::Button;
+
+const MAGIC: &str = "sapphire-input";
+/// Bumped whenever the format changes, as old recordings won't replay correctly.
+const VERSION: u32 = 3;
+
+/// The input state produced by one call to [`Input::update`](super::Input::update).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(super) struct Frame {
+    /// Counts up from 0, so missing or reordered frames can be detected.
+    pub frame: u64,
+    pub pressed: Vec<Button>,
+    pub mouse: (i32, i32),
+    pub scroll: f64,
+    /// Every key held, including ones bound to buttons.
+    pub keys: Vec<KeyCode>,
+    /// Text typed while text input was on.
+    pub text: String,
+    pub reset: bool,
+}
+
+/// The first line of a recording.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Header {
+    magic: String,
+    version: u32,
+    /// What the scripts' random number generator was seeded with. Defaulted so older versions are still
+    /// recognized as recordings (and rejected for their version).
+    #[serde(default)]
+    seed: u64,
+}
+
+/// The last line of a recording, written when recording stops.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Footer {
+    frames: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Failed to read input recording {path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{path} is not an input recording")]
+    NotARecording { path: Utf8PathBuf },
+    #[error("{path} was recorded with version {version} of the input format, but only version {VERSION} is supported")]
+    UnsupportedVersion { path: Utf8PathBuf, version: u32 },
+    #[error("Line {line} of {path} is invalid: {source}")]
+    Invalid {
+        path: Utf8PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("Desync: {path} skips from frame {expected} to frame {found}")]
+    MissingFrames {
+        path: Utf8PathBuf,
+        expected: u64,
+        found: u64,
+    },
+    #[error("Desync: {path} says it has {expected} frames, but it has {found}")]
+    FrameCountMismatch {
+        path: Utf8PathBuf,
+        expected: u64,
+        found: u64,
+    },
+    #[error("Desync: the game stopped after {played} frames, but {path} has {total}")]
+    StoppedEarly {
+        path: Utf8PathBuf,
+        played: u64,
+        total: u64,
+    },
+}
+
+/// Writes each frame of input to a file as it happens, one JSON object per line.
+pub(super) struct Recorder {
+    writer: BufWriter<File>,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn create(path: &Utf8Path, seed: u64) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = Header {
+            magic: MAGIC.to_string(),
+            version: VERSION,
+            seed,
+        };
+        write_line(&mut writer, &header)?;
+
+        Ok(Self { writer, frames: 0 })
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> std::io::Result<()> {
+        write_line(&mut self.writer, frame)?;
+        self.frames += 1;
+        // flushed every frame so a crash doesn't lose the input that caused it
+        self.writer.flush()
+    }
+
+    /// Writes the footer. If the game crashes before this, the recording can still be replayed.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        write_line(
+            &mut self.writer,
+            &Footer {
+                frames: self.frames,
+            },
+        )?;
+        self.writer.flush()
+    }
+}
+
+/// A seed for a new recording, which only needs to differ between runs.
+pub(super) fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+fn write_line(writer: &mut impl Write, value: &impl serde::Serialize) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")
+}
+
+/// Plays back a recording made by [`Recorder`].
+pub(super) struct Replayer {
+    pub path: Utf8PathBuf,
+    pub seed: u64,
+    frames: std::vec::IntoIter<Frame>,
+    total: u64,
+}
+
+impl Replayer {
+    /// Reads a whole recording, checking that no frames are missing.
+    pub fn open(path: &Utf8Path) -> Result<Self, ReplayError> {
+        let io_error = |source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let file = File::open(path).map_err(io_error)?;
+        let mut lines = BufReader::new(file).lines().enumerate();
+
+        let Some((_, header)) = lines.next() else {
+            return Err(ReplayError::NotARecording {
+                path: path.to_path_buf(),
+            });
+        };
+        let header: Header = serde_json::from_str(&header.map_err(io_error)?).map_err(|_| {
+            ReplayError::NotARecording {
+                path: path.to_path_buf(),
+            }
+        })?;
+        if header.magic != MAGIC {
+            return Err(ReplayError::NotARecording {
+                path: path.to_path_buf(),
+            });
+        }
+        if header.version != VERSION {
+            return Err(ReplayError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                version: header.version,
+            });
+        }
+
+        let mut frames = Vec::new();
+        let mut footer = None;
+        for (index, line) in lines {
+            let line = line.map_err(io_error)?;
+            let invalid = |source| ReplayError::Invalid {
+                path: path.to_path_buf(),
+                line: index + 1,
+                source,
+            };
+            // the footer is the only line without a frame number
+            if let Ok(parsed) = serde_json::from_str::<Footer>(&line) {
+                footer = Some(parsed);
+                break;
+            }
+            let frame: Frame = serde_json::from_str(&line).map_err(invalid)?;
+
+            let expected = frames.len() as u64;
+            if frame.frame != expected {
+                return Err(ReplayError::MissingFrames {
+                    path: path.to_path_buf(),
+                    expected,
+                    found: frame.frame,
+                });
+            }
+            frames.push(frame);
+        }
+
+        let total = frames.len() as u64;
+        match footer {
+            Some(footer) if footer.frames != total => {
+                return Err(ReplayError::FrameCountMismatch {
+                    path: path.to_path_buf(),
+                    expected: footer.frames,
+                    found: total,
+                })
+            }
+            Some(_) => {}
+            None => eprintln!("{path} has no footer (the game probably crashed while recording)"),
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            seed: header.seed,
+            frames: frames.into_iter(),
+            total,
+        })
+    }
+
+    /// The next recorded frame, or `None` once the recording has run out.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        self.frames.next()
+    }
+
+    /// How many frames haven't been played yet.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Checks that the whole recording was played, for when the game stops.
+    pub fn finish(self) -> Result<(), ReplayError> {
+        let played = self.total - self.remaining() as u64;
+        if played < self.total {
+            return Err(ReplayError::StoppedEarly {
+                path: self.path,
+                played,
+                total: self.total,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyBind;
+
+    fn temp_path(name: &str) -> Utf8PathBuf {
+        Utf8PathBuf::from_path_buf(std::env::temp_dir())
+            .unwrap()
+            .join(format!("sapphire-{name}-{}.jsonl", std::process::id()))
+    }
+
+    fn frame(frame: u64) -> Frame {
+        Frame {
+            frame,
+            pressed: vec![
+                Button::KeyBind(KeyBind::Action),
+                Button::KeyCode(KeyCode::KeyM),
+            ],
+            mouse: (12, -3),
+            scroll: 1.5,
+            keys: vec![KeyCode::KeyZ, KeyCode::KeyM],
+            text: "m".to_string(),
+            reset: false,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+        let mut recorder = Recorder::create(&path, 1234).unwrap();
+        for i in 0..3 {
+            recorder.record(&frame(i)).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let mut replayer = Replayer::open(&path).unwrap();
+        assert_eq!(replayer.total, 3);
+        assert_eq!(replayer.seed, 1234);
+        for i in 0..3 {
+            assert_eq!(replayer.next_frame(), Some(frame(i)));
+        }
+        assert_eq!(replayer.next_frame(), None);
+        assert!(replayer.finish().is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stopping_early_is_a_desync() {
+        let path = temp_path("stopped-early");
+        let mut recorder = Recorder::create(&path, 0).unwrap();
+        for i in 0..3 {
+            recorder.record(&frame(i)).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let mut replayer = Replayer::open(&path).unwrap();
+        replayer.next_frame();
+        let error = replayer.finish().err().unwrap();
+        assert!(matches!(
+            error,
+            ReplayError::StoppedEarly {
+                played: 1,
+                total: 3,
+                ..
+            }
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_frames_are_a_desync() {
+        let path = temp_path("missing-frames");
+        let mut recorder = Recorder::create(&path, 0).unwrap();
+        recorder.record(&frame(0)).unwrap();
+        recorder.record(&frame(2)).unwrap();
+        recorder.finish().unwrap();
+
+        let error = Replayer::open(&path).err().unwrap();
+        assert!(matches!(
+            error,
+            ReplayError::MissingFrames {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn wrong_frame_count_is_a_desync() {
+        let path = temp_path("frame-count");
+        let mut recorder = Recorder::create(&path, 0).unwrap();
+        recorder.record(&frame(0)).unwrap();
+        recorder.frames = 5;
+        recorder.finish().unwrap();
+
+        let error = Replayer::open(&path).err().unwrap();
+        assert!(matches!(error, ReplayError::FrameCountMismatch { .. }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let path = temp_path("version");
+        std::fs::write(&path, "{\"magic\":\"sapphire-input\",\"version\":99}\n").unwrap();
+        let error = Replayer::open(&path).err().unwrap();
+        assert!(matches!(
+            error,
+            ReplayError::UnsupportedVersion { version: 99, .. }
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
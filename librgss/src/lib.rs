@@ -2,7 +2,15 @@ mod arenas;
 pub use arenas::Arenas;
 
 mod audio;
-pub use audio::Audio;
+pub use audio::{
+    output_devices as audio_output_devices, Audio, Channel as AudioChannel, Error as AudioError,
+    PlaybackStatus, VolumeCategory, Volumes,
+};
+
+mod config;
+pub use config::{
+    AudioBackend, AudioConfig, AudioErrorMode, Config, InputConfig, RgssVersion, SeEviction,
+};
 
 mod data;
 pub use data::{Color, Rect, SharedColor, SharedRect, SharedTable, SharedTone, Table, Tone};
@@ -20,7 +28,9 @@ mod graphics;
 pub use graphics::{Bitmap, Graphics, Plane, Sprite, Tilemap, Viewport, Window, WindowData};
 
 mod input;
-pub use input::{Button, Input, KeyBind, NamedButton};
+pub use input::{
+    Bindings, Button, GamepadButton, Input, KeyBind, NamedButton, PhysicalInput, ReplayError,
+};
 
 pub fn join_handle_result_to_eyre<T>(result: std::thread::Result<T>) -> color_eyre::Result<T> {
     result.map_err(|e| {
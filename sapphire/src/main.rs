@@ -45,24 +45,36 @@ fn run() -> color_eyre::Result<()> {
     println!("Sapphire version {}", env!("CARGO_PKG_VERSION"));
 
     let (event_loop, events) = librgss::EventLoop::new()?;
-    let input = librgss::Input::new(events);
 
     // temporary hack
     std::env::set_current_dir("OSFM/")?;
     let filesystem = librgss::FileSystem::new(".", None).map(Arc::new)?;
 
-    let (audio, audio_thread) = librgss::Audio::new(filesystem.clone())?;
+    let mut config = librgss::Config::load("sapphire.toml").note("while loading sapphire.toml")?;
+    config.apply_args(std::env::args().skip(1));
+    config.detect_game(&filesystem);
+    println!("Running as {:?}", config.rgss_version());
+
+    // the editor writes the battle test party and troop to BT_* files before launching us
+    let battle_test_actors = format!("Data/BT_Actors.{}", config.rgss_version().data_extension());
+    if config.battle_test && filesystem.read_file(battle_test_actors).is_err() {
+        return Err(color_eyre::eyre::eyre!("battle test data is missing")
+            .suggestion("battle test must be launched from the editor"));
+    }
+
+    let (audio, audio_thread) = librgss::Audio::new(filesystem.clone(), config.audio.clone())?;
     let mut arenas = librgss::Arenas::default();
     // we block on graphics because creating graphics is an async operation.
     // if we were to be running this on say, the browser, we would need to actually await this (rather than using block_on)
     let graphics =
         librgss::Graphics::new(&mut arenas, &event_loop, filesystem.clone()).block_on()?;
 
+    let input = librgss::Input::new(events, &config.input, graphics.screen_size())?;
     let fonts = librgss::Fonts::new();
 
     #[cfg(feature = "magnus")]
     let bindings_thread =
-        sapphire_binding_magnus::start(audio, arenas, graphics, fonts, input, filesystem);
+        sapphire_binding_magnus::start(audio, arenas, graphics, fonts, input, filesystem, config);
 
     // run the event loop to completion. for compatibility reasons, this blocks the main thread
     event_loop.run()?;